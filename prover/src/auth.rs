@@ -0,0 +1,504 @@
+//! API-key authentication
+//!
+//! Off unless an admin key is configured. Once it is, every `/api/v1`
+//! request needs a key, sent as `Authorization: Bearer <key>` or
+//! `X-API-Key: <key>`; browsers' `EventSource` and `WebSocket` cannot set
+//! headers, so `/api/v1/events` and `/api/v1/ws` also take `?api_key=`.
+//!
+//! Each key belongs to a principal. Submissions record it as the submitter,
+//! status changes as the actor and circuits as the owner, and only the
+//! submitter may delete a SNARK or change its status. Keys with the admin
+//! role, and the configured admin key, may act for anyone and manage keys
+//! through `/api/v1/admin/keys`. The kernel stores keys by their SHA-256,
+//! so a key is only ever shown when it is created.
+//!
+//! Keys restrict writes, not reads: any key may list and fetch every SNARK
+//! and follow every SNARK's events on the SSE and WebSocket streams.
+
+use std::collections::HashMap;
+
+use axum::extract::{Path as AxumPath, Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{Noun, D, T};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cord_to_string, error_response, handle_effects, peek_found, peek_json, poke_kernel,
+    string_to_cord, SharedState,
+};
+
+/// Shortest admin key accepted in the configuration
+pub const MIN_ADMIN_KEY_LEN: usize = 16;
+
+/// Prefix of generated keys, so they are easy to spot in logs and configs
+const KEY_PREFIX: &str = "prv_";
+
+/// Principal name of the configured admin key
+const ADMIN_PRINCIPAL: &str = "admin";
+
+/// Longest principal name
+const MAX_PRINCIPAL_LEN: usize = 64;
+
+/// Names the server records for itself, which no key may take
+const RESERVED_PRINCIPALS: [&str; 3] = [ADMIN_PRINCIPAL, crate::VERIFIER_ACTOR, crate::ANONYMOUS_ACTOR];
+
+/// Routes that may carry the key in the query string
+const QUERY_KEY_PATHS: [&str; 2] = ["/api/v1/events", "/api/v1/ws"];
+
+/// What a key may do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Acts only as its own principal
+    #[default]
+    User,
+    /// Acts for anyone and manages keys
+    Admin,
+}
+
+/// Who a request was made by
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Principal {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+}
+
+/// A request its principal may not make, answered with 403
+#[derive(Debug)]
+pub struct Forbidden(String);
+
+impl IntoResponse for Forbidden {
+    fn into_response(self) -> Response {
+        error_response(StatusCode::FORBIDDEN, &self.0)
+    }
+}
+
+/// Key creation request
+#[derive(Debug, Deserialize)]
+pub struct NewKey {
+    principal: String,
+    #[serde(default)]
+    role: Role,
+}
+
+/// Hex SHA-256 of a key, as stored in the kernel
+fn key_hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Require a valid key on API requests when authentication is on
+///
+/// The key's principal is added to the request's extensions for handlers.
+pub async fn require(State(state): State<SharedState>, mut request: Request, next: Next) -> Response {
+    let Some(admin_hash) = &state.admin_key_hash else {
+        return next.run(request).await;
+    };
+    let Some(key) = presented_key(&request) else {
+        return unauthorized("API key required");
+    };
+
+    let hash = key_hash(&key);
+    let principal = if hash == *admin_hash {
+        Principal { name: ADMIN_PRINCIPAL.to_string(), role: Role::Admin }
+    } else {
+        match lookup(&state, &hash).await {
+            Ok(Some(principal)) => principal,
+            Ok(None) => return unauthorized("Invalid API key"),
+            Err(e) => {
+                log::error!("Error reading API key: {}", e);
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read API key");
+            }
+        }
+    };
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+/// The key sent with a request, if any
+fn presented_key(request: &Request) -> Option<String> {
+    if let Some(key) = header_key(request.headers()) {
+        return Some(key);
+    }
+    if !QUERY_KEY_PATHS.contains(&request.uri().path()) {
+        return None;
+    }
+    let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(request.uri()).ok()?;
+    params.remove("api_key").filter(|key| !key.is_empty())
+}
+
+fn header_key(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let key = bearer.or_else(|| headers.get("x-api-key").and_then(|value| value.to_str().ok()))?;
+    let key = key.trim();
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+fn unauthorized(message: &str) -> Response {
+    let mut response = error_response(StatusCode::UNAUTHORIZED, message);
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// The name to record for a request that claims to act as `claimed`
+///
+/// Without authentication the claim stands. Users act as themselves, and
+/// may leave the claim out; admins may act for anyone.
+pub fn act_as(principal: Option<&Principal>, claimed: &str, field: &str) -> Result<String, Forbidden> {
+    let claimed = claimed.trim();
+    match principal {
+        None => Ok(claimed.to_string()),
+        Some(principal) if claimed.is_empty() => Ok(principal.name.clone()),
+        Some(principal) if principal.is_admin() || claimed == principal.name => Ok(claimed.to_string()),
+        Some(principal) => Err(Forbidden(format!(
+            "{} must be your principal, {:?}",
+            field, principal.name
+        ))),
+    }
+}
+
+/// Principal that must own a SNARK to change it, or `None` if anyone may
+pub fn owner_required(principal: Option<&Principal>) -> Option<&str> {
+    principal.filter(|p| !p.is_admin()).map(|p| p.name.as_str())
+}
+
+fn require_admin(principal: Option<&Principal>) -> Result<(), Forbidden> {
+    match principal {
+        Some(principal) if principal.is_admin() => Ok(()),
+        Some(_) => Err(Forbidden("Admin role required".to_string())),
+        None => Err(Forbidden(
+            "Authentication is off; set admin_key to manage API keys".to_string(),
+        )),
+    }
+}
+
+/// Whether a principal name is printable and short enough to log
+fn valid_principal(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_PRINCIPAL_LEN
+        && name.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// Create an API key
+///
+/// Answers 201 with the key's record and the key itself, which is not
+/// stored and cannot be shown again.
+pub async fn create_key(
+    State(state): State<SharedState>,
+    principal: Option<Extension<Principal>>,
+    Json(new_key): Json<NewKey>,
+) -> Response {
+    if let Err(forbidden) = require_admin(principal.as_deref()) {
+        return forbidden.into_response();
+    }
+    let name = new_key.principal.trim();
+    if !valid_principal(name) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Principal must be 1 to {} visible ASCII characters", MAX_PRINCIPAL_LEN),
+        );
+    }
+    if RESERVED_PRINCIPALS.contains(&name) {
+        return error_response(StatusCode::BAD_REQUEST, &format!("Principal {:?} is reserved", name));
+    }
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let key = format!("{}{}", KEY_PREFIX, hex::encode(secret));
+    let hash = key_hash(&key);
+
+    let mut poke_slab = NounSlab::new();
+
+    // [%add-api-key hash=@t id=@t principal=@t role=?(%user %admin)]
+    let id = string_to_cord(&mut poke_slab, &hash[..16]);
+    let hash = string_to_cord(&mut poke_slab, &hash);
+    let name = string_to_cord(&mut poke_slab, name);
+    let role = match new_key.role {
+        Role::User => D(b"user" as &[u8]),
+        Role::Admin => D(b"admin" as &[u8]),
+    };
+    let cause = T(&mut poke_slab, &[D(b"add-api-key" as &[u8]), hash, id, name, role]);
+    poke_slab.set_root(cause);
+
+    let effects = match poke_kernel(&state, poke_slab, "Failed to create API key").await {
+        Ok(effects) => effects,
+        Err(response) => return response,
+    };
+    let Some(response) = handle_effects(effects) else {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, "No response from kernel");
+    };
+    if response.status() != StatusCode::CREATED {
+        return response;
+    }
+    // Hand the key back alongside the kernel's record
+    let status = response.status();
+    let record = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+        Err(_) => serde_json::Value::Null,
+    };
+    let mut body = match record {
+        serde_json::Value::Object(fields) => fields,
+        _ => serde_json::Map::new(),
+    };
+    body.insert("key".to_string(), serde_json::Value::String(key));
+    (status, Json(body)).into_response()
+}
+
+/// List API keys, oldest first, without the keys themselves
+pub async fn list_keys(
+    State(state): State<SharedState>,
+    principal: Option<Extension<Principal>>,
+) -> Response {
+    if let Err(forbidden) = require_admin(principal.as_deref()) {
+        return forbidden.into_response();
+    }
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"api-keys" as &[u8]), D(0)]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Not found").await
+}
+
+/// Revoke an API key by ID
+pub async fn revoke_key(
+    State(state): State<SharedState>,
+    principal: Option<Extension<Principal>>,
+    AxumPath(id): AxumPath<String>,
+) -> Response {
+    if let Err(forbidden) = require_admin(principal.as_deref()) {
+        return forbidden.into_response();
+    }
+    let mut poke_slab = NounSlab::new();
+    let id = string_to_cord(&mut poke_slab, &id);
+    let cause = T(&mut poke_slab, &[D(b"revoke-api-key" as &[u8]), id]);
+    poke_slab.set_root(cause);
+
+    let effects = match poke_kernel(&state, poke_slab, "Failed to revoke API key").await {
+        Ok(effects) => effects,
+        Err(response) => return response,
+    };
+    handle_effects(effects).unwrap_or_else(|| {
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "No response from kernel")
+    })
+}
+
+/// Peek the principal and role of a key by its hash
+async fn lookup(state: &SharedState, hash: &str) -> Result<Option<Principal>, String> {
+    let mut peek_slab = NounSlab::new();
+    let hash = string_to_cord(&mut peek_slab, hash);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"api-key" as &[u8]), hash, D(0)]);
+    peek_slab.set_root(path);
+
+    let Some(found) = peek_found(state, peek_slab).await? else {
+        return Ok(None);
+    };
+    parse(found).map(Some).ok_or_else(|| "Invalid response from kernel".to_string())
+}
+
+/// Parse `[principal=@t role=?(%user %admin)]`
+fn parse(found: Noun) -> Option<Principal> {
+    let cell = found.as_cell().ok()?;
+    let role = if cell.tail().eq_bytes(b"admin") { Role::Admin } else { Role::User };
+    Some(Principal { name: cord_to_string(cell.head())?, role })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ark_serialize::Compress;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use reqwest::Method;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::testing;
+
+    const ADMIN_KEY: &str = "admin-key-for-tests";
+
+    /// The API with authentication on, served on a free port
+    struct Server {
+        url: String,
+        http: reqwest::Client,
+        _jobs: mpsc::Receiver<u64>,
+        _dir: TempDir,
+    }
+
+    impl Server {
+        async fn start() -> Self {
+            let (mut state, jobs, dir) = testing::state_with_jobs(testing::no_webhooks(), 8).await;
+            Arc::get_mut(&mut state).expect("state is not shared yet").admin_key_hash = Some(key_hash(ADMIN_KEY));
+            let listener = TcpListener::bind("127.0.0.1:0").await.expect("port is free");
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let app = crate::router(state, dir.path());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            let http = reqwest::Client::builder().no_proxy().build().unwrap();
+            Server { url, http, _jobs: jobs, _dir: dir }
+        }
+
+        fn request(&self, method: Method, path: &str, key: Option<&str>) -> reqwest::RequestBuilder {
+            let request = self.http.request(method, format!("{}{}", self.url, path));
+            match key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            }
+        }
+
+        async fn call(&self, method: Method, path: &str, key: Option<&str>, body: Option<Value>) -> (u16, Value) {
+            let mut request = self.request(method, path, key);
+            if let Some(body) = body {
+                request = request.json(&body);
+            }
+            let response = request.send().await.expect("server answers");
+            let status = response.status().as_u16();
+            (status, response.json().await.unwrap_or(Value::Null))
+        }
+
+        /// Create a key as the admin, returning the key and its ID
+        async fn create_key(&self, principal: &str, role: &str) -> (String, String) {
+            let body = json!({ "principal": principal, "role": role });
+            let (status, body) = self.call(Method::POST, "/api/v1/admin/keys", Some(ADMIN_KEY), Some(body)).await;
+            assert_eq!(status, 201, "{}", body);
+            (body["key"].as_str().unwrap().to_string(), body["id"].as_str().unwrap().to_string())
+        }
+
+        /// Submit the Groth16 fixture, naming `submitter` if not empty
+        async fn submit(&self, key: &str, submitter: &str) -> (u16, Value) {
+            let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+            let body = json!({
+                "proof": BASE64.encode(fixture.proof),
+                "verification_key": BASE64.encode(fixture.vk),
+                "public_inputs": ["15"],
+                "proof_system": "groth16",
+                "submitter": submitter,
+            });
+            self.call(Method::POST, "/api/v1/snark", Some(key), Some(body)).await
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_need_a_valid_key() {
+        let server = Server::start().await;
+        let (status, _) = server.call(Method::GET, "/api/v1/snarks", None, None).await;
+        assert_eq!(status, 401);
+        let (status, _) = server.call(Method::GET, "/api/v1/snarks", Some("prv_unknown"), None).await;
+        assert_eq!(status, 401);
+        let (status, _) = server.call(Method::GET, "/api/v1/snarks", Some(ADMIN_KEY), None).await;
+        assert_eq!(status, 200);
+
+        let response = server.request(Method::GET, "/api/v1/snarks", None).send().await.unwrap();
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+        let response = server
+            .request(Method::GET, "/api/v1/snarks", None)
+            .header("X-API-Key", ADMIN_KEY)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        // Only the streams take the key in the query string
+        let path = format!("/api/v1/snarks?api_key={}", ADMIN_KEY);
+        let (status, _) = server.call(Method::GET, &path, None, None).await;
+        assert_eq!(status, 401);
+        let path = format!("/api/v1/events?api_key={}", ADMIN_KEY);
+        let response = server.request(Method::GET, &path, None).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn only_admins_manage_keys() {
+        let server = Server::start().await;
+        let (user, id) = server.create_key("alice", "user").await;
+        let (admin, _) = server.create_key("root", "admin").await;
+
+        let (status, _) = server.call(Method::GET, "/api/v1/admin/keys", Some(&user), None).await;
+        assert_eq!(status, 403);
+        let body = json!({ "principal": "mallory", "role": "admin" });
+        let (status, _) = server.call(Method::POST, "/api/v1/admin/keys", Some(&user), Some(body)).await;
+        assert_eq!(status, 403);
+        let (status, _) = server.call(Method::DELETE, &format!("/api/v1/admin/keys/{}", id), Some(&user), None).await;
+        assert_eq!(status, 403);
+
+        // Admin keys made through the API are as good as the configured one
+        let (status, body) = server.call(Method::GET, "/api/v1/admin/keys", Some(&admin), None).await;
+        assert_eq!(status, 200);
+        assert_eq!(body.to_string().matches("\"principal\"").count(), 2, "{}", body);
+        assert!(!body.to_string().contains(&user), "keys are never listed");
+    }
+
+    #[tokio::test]
+    async fn reserved_principals_cannot_have_keys() {
+        let server = Server::start().await;
+        for principal in ["admin", "verifier", "anonymous", " admin "] {
+            let body = json!({ "principal": principal });
+            let (status, body) = server.call(Method::POST, "/api/v1/admin/keys", Some(ADMIN_KEY), Some(body)).await;
+            assert_eq!(status, 400, "{:?}: {}", principal, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn users_change_only_their_own_snarks() {
+        let server = Server::start().await;
+        let (alice, _) = server.create_key("alice", "user").await;
+        let (bob, _) = server.create_key("bob", "user").await;
+
+        let (status, body) = server.submit(&bob, "alice").await;
+        assert_eq!(status, 403, "{}", body);
+        let (status, body) = server.submit(&alice, "").await;
+        assert_eq!(status, 201, "{}", body);
+        let id = body["id"].as_u64().unwrap();
+        let snark = format!("/api/v1/snark/{}", id);
+        let (_, body) = server.call(Method::GET, &snark, Some(&bob), None).await;
+        assert_eq!(body["submitter"], "alice", "anyone may read");
+
+        let update = json!({ "status": "verifying" });
+        let status_path = format!("{}/status", snark);
+        let (status, _) = server.call(Method::PATCH, &status_path, Some(&bob), Some(update.clone())).await;
+        assert_eq!(status, 403);
+        let (status, _) = server.call(Method::DELETE, &snark, Some(&bob), None).await;
+        assert_eq!(status, 403);
+        let (status, _) = server.call(Method::DELETE, &format!("{}?actor=alice", snark), Some(&bob), None).await;
+        assert_eq!(status, 403);
+
+        let (status, body) = server.call(Method::PATCH, &status_path, Some(&alice), Some(update)).await;
+        assert_eq!(status, 200, "{}", body);
+        let (status, _) = server.call(Method::DELETE, &snark, Some(&alice), None).await;
+        assert_eq!(status, 200);
+    }
+
+    #[tokio::test]
+    async fn revoked_keys_stop_working() {
+        let server = Server::start().await;
+        let (key, id) = server.create_key("alice", "user").await;
+        let (status, _) = server.call(Method::GET, "/api/v1/snarks", Some(&key), None).await;
+        assert_eq!(status, 200);
+
+        let revoke = format!("/api/v1/admin/keys/{}", id);
+        let (status, _) = server.call(Method::DELETE, &revoke, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, 200);
+        let (status, _) = server.call(Method::GET, "/api/v1/snarks", Some(&key), None).await;
+        assert_eq!(status, 401);
+        let (status, _) = server.call(Method::DELETE, &revoke, Some(ADMIN_KEY), None).await;
+        assert_eq!(status, 404);
+    }
+}
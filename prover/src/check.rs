@@ -0,0 +1,158 @@
+//! Snapshot upgrade check
+//!
+//! `--check-snapshot` restores a saved snapshot of any state version into a
+//! freshly booted kernel and compares every SNARK in the snapshot with what
+//! the kernel holds afterwards. Run it against a copy of `state.jam` before
+//! deploying a kernel that changes `+$state`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+use bytes::Bytes;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{Noun, D, T};
+use nockapp::NockApp;
+
+use crate::{cord_to_string, decode_peek, handle_effects, list_items};
+
+/// Restore the snapshot at `path` and check no SNARK was lost or changed
+pub async fn run(app: &mut NockApp, path: &Path) -> Result<(), Box<dyn Error>> {
+    let jam = Bytes::from(std::fs::read(path)?);
+
+    // SNARKs as the snapshot holds them: [version snarks=(map @ud snark-entry) ...]
+    let mut saved_slab = NounSlab::new();
+    let saved = saved_slab
+        .cue_into(jam.clone())
+        .map_err(|e| format!("Unreadable snapshot {:?}: {:?}", path, e))?;
+    let (version, before) = saved_entries(saved).ok_or("Snapshot is not a prover state")?;
+
+    let mut poke_slab = NounSlab::new();
+    let saved = poke_slab
+        .cue_into(jam)
+        .map_err(|e| format!("Unreadable snapshot {:?}: {:?}", path, e))?;
+    let cause = T(&mut poke_slab, &[D(b"restore" as &[u8]), saved]);
+    poke_slab.set_root(cause);
+    let effects = app
+        .poke(poke_slab)
+        .await
+        .map_err(|e| format!("Kernel rejected {} snapshot: {:?}", version, e))?;
+    handle_effects(effects);
+
+    // SNARKs as the upgraded kernel holds them
+    let mut peek_slab = NounSlab::new();
+    let peek_path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"snarks" as &[u8]), D(0)]);
+    peek_slab.set_root(peek_path);
+    let result = app.peek(peek_slab).await?;
+    let after: HashMap<u64, Bytes> = decode_peek(result)
+        .flatten()
+        .and_then(|list| {
+            list_items(list)
+                .into_iter()
+                .map(|pair| split_entry(pair).map(|(id, entry)| (id, jam_noun(entry))))
+                .collect()
+        })
+        .ok_or("Kernel returned no SNARK list")?;
+
+    let mut problems = 0;
+    for (id, entry) in &before {
+        match after.get(id) {
+            Some(upgraded) if *upgraded == jam_noun(*entry) => {}
+            Some(_) => {
+                log::error!("SNARK #{} changed during the upgrade", id);
+                problems += 1;
+            }
+            None => {
+                log::error!("SNARK #{} is missing after the upgrade", id);
+                problems += 1;
+            }
+        }
+    }
+    if after.len() != before.len() {
+        log::error!("Snapshot holds {} SNARKs, upgraded kernel {}", before.len(), after.len());
+        problems += 1;
+    }
+
+    if problems > 0 {
+        return Err(format!("{} problem(s) upgrading {} snapshot {:?}", problems, version, path).into());
+    }
+    log::info!("All {} SNARKs in {} snapshot {:?} preserved", before.len(), version, path);
+    Ok(())
+}
+
+/// State version and `(id, entry)` pairs of a saved state
+fn saved_entries(state: Noun) -> Option<(String, Vec<(u64, Noun)>)> {
+    let cell = state.as_cell().ok()?;
+    let version = cord_to_string(cell.head())?;
+    let snarks = cell.tail().as_cell().ok()?.head();
+    let entries = map_items(snarks)?
+        .into_iter()
+        .map(split_entry)
+        .collect::<Option<Vec<_>>>()?;
+    Some((version, entries))
+}
+
+/// Items of a Hoon map, a treap of `[n=item l=map r=map]` nodes
+fn map_items(map: Noun) -> Option<Vec<Noun>> {
+    let mut items = Vec::new();
+    let mut stack = vec![map];
+    while let Some(node) = stack.pop() {
+        // `~` marks an empty subtree
+        let Ok(cell) = node.as_cell() else {
+            continue;
+        };
+        let children = cell.tail().as_cell().ok()?;
+        items.push(cell.head());
+        stack.push(children.head());
+        stack.push(children.tail());
+    }
+    Some(items)
+}
+
+/// Split `[id=@ud entry=snark-entry]`
+fn split_entry(pair: Noun) -> Option<(u64, Noun)> {
+    let cell = pair.as_cell().ok()?;
+    let id = cell.head().as_atom().ok()?.as_u64().ok()?;
+    Some((id, cell.tail()))
+}
+
+/// Jam a noun so entries can be compared byte for byte
+fn jam_noun(noun: Noun) -> Bytes {
+    let mut slab = NounSlab::new();
+    slab.copy_into(noun);
+    slab.jam()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Segment};
+
+    /// v1 state with three SNARKs, written by `tests/fixtures/make_state_v1.py`
+    const STATE_V1: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/prover/tests/fixtures/state-v1.jam");
+
+    #[tokio::test]
+    async fn v1_snapshot_upgrades_to_current_state() {
+        let mut app = testing::kernel().await;
+        run(&mut app, Path::new(STATE_V1)).await.expect("every SNARK survives the upgrade");
+
+        let state = testing::peek(&mut app, &[Segment::Text("state")]).await.expect("state");
+        let version = state.as_cell().ok().and_then(|cell| cord_to_string(cell.head()));
+        assert_eq!(version.as_deref(), Some("v8"));
+
+        // Entries are found by ID, so the map survived as a map
+        for (id, submitter, status) in [(1, "alice", "pending"), (2, "bob", "failed"), (3, "José", "verified")] {
+            let detail = testing::peek_json(&mut app, &[Segment::Text("snark"), Segment::Number(id)]).await;
+            assert_eq!(detail["submitter"], submitter, "SNARK #{}", id);
+            assert_eq!(detail["status"], status, "SNARK #{}", id);
+        }
+        assert_eq!(
+            testing::peek_json(&mut app, &[Segment::Text("snark"), Segment::Number(2)]).await["error_message"],
+            "bad proof"
+        );
+
+        // IDs carry on from the snapshot's next-id, past the deleted #4
+        let id = testing::submit(&mut app, "carol", "", "after-upgrade").await;
+        assert_eq!(id, 5);
+    }
+}
@@ -0,0 +1,337 @@
+//! Circuit registry
+//!
+//! A circuit names a registered verification key and describes the public
+//! inputs its proofs carry, each with a name and a type. Submissions naming
+//! a circuit use its key and have their inputs checked against the schema
+//! before they are stored; `GET /api/v1/snarks?circuit=<name>` lists them.
+//!
+//! Input types are `field` (any element of the proof system's field),
+//! `bool` (0 or 1) and `u<bits>` for unsigned integers up to 256 bits.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use axum::extract::{Path as AxumPath, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{D, T};
+use serde::Deserialize;
+
+use crate::{
+    auth, cord_to_string, error_response, handle_effects, peek_found, peek_json, poke_kernel,
+    string_to_cord, verify, vks, SharedState,
+};
+
+/// Longest circuit name
+const MAX_NAME_LEN: usize = 64;
+
+/// Circuit registration request
+#[derive(Debug, Deserialize)]
+pub struct Registration {
+    name: String,
+    /// Registered verification key
+    vk_id: String,
+    /// Defaults to the key's curve
+    curve: Option<String>,
+    inputs: Vec<InputSpec>,
+    /// Defaults to the caller's principal when authentication is on
+    #[serde(default)]
+    owner: String,
+}
+
+/// Name and type of one public input
+#[derive(Debug, Deserialize)]
+pub struct InputSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// The parts of a circuit a submission needs
+#[derive(Debug, Deserialize)]
+pub struct CircuitRecord {
+    pub name: String,
+    pub proof_system: String,
+    pub vk_id: String,
+    pub inputs: Vec<InputSpec>,
+}
+
+/// Type of a public input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputType {
+    Field,
+    Bool,
+    Uint(u64),
+}
+
+impl FromStr for InputType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "field" => Ok(InputType::Field),
+            "bool" => Ok(InputType::Bool),
+            _ => s
+                .strip_prefix('u')
+                .and_then(|bits| bits.parse().ok())
+                .filter(|bits| (1..=256).contains(bits))
+                .map(InputType::Uint)
+                .ok_or_else(|| format!("Unknown input type {:?}; use field, bool or u1 to u256", s)),
+        }
+    }
+}
+
+impl InputType {
+    /// Whether a value fits the type; fields are range-checked against the
+    /// proof system later
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            InputType::Field => true,
+            InputType::Bool => matches!(value.trim(), "0" | "1"),
+            InputType::Uint(bits) => verify::parse_uint(value).is_some_and(|v| v.bits() <= bits),
+        }
+    }
+}
+
+/// Check submitted public inputs against a circuit's schema
+pub fn check_inputs(circuit: &CircuitRecord, inputs: &[String]) -> Result<(), String> {
+    if inputs.len() != circuit.inputs.len() {
+        return Err(format!(
+            "Circuit {} takes {} public inputs, got {}",
+            circuit.name,
+            circuit.inputs.len(),
+            inputs.len()
+        ));
+    }
+    for (i, (spec, value)) in circuit.inputs.iter().zip(inputs).enumerate() {
+        // Types were checked at registration, so this only fails on a
+        // damaged record; refuse rather than let the input through
+        let kind = spec.kind.parse::<InputType>().map_err(|_| {
+            format!(
+                "Circuit {} has an unreadable type for public input {} ({}): {:?}",
+                circuit.name, i, spec.name, spec.kind
+            )
+        })?;
+        if !kind.accepts(value) {
+            return Err(format!(
+                "Public input {} ({}) is not a {}: {:?}",
+                i, spec.name, spec.kind, value
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a circuit name is safe to use in URLs
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Register a circuit
+pub async fn register(
+    State(state): State<SharedState>,
+    principal: Option<Extension<auth::Principal>>,
+    Json(registration): Json<Registration>,
+) -> Response {
+    if !valid_name(&registration.name) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Circuit name must be 1 to {} letters, digits, '-', '_' or '.'",
+                MAX_NAME_LEN
+            ),
+        );
+    }
+    let owner = match auth::act_as(principal.as_deref(), &registration.owner, "owner") {
+        Ok(owner) => owner,
+        Err(forbidden) => return forbidden.into_response(),
+    };
+    if owner.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Owner is required");
+    }
+    let mut names = HashSet::new();
+    for spec in &registration.inputs {
+        if spec.name.trim().is_empty() {
+            return error_response(StatusCode::BAD_REQUEST, "Every input needs a name");
+        }
+        if !names.insert(spec.name.as_str()) {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Input {:?} is named twice", spec.name),
+            );
+        }
+        if let Err(message) = spec.kind.parse::<InputType>() {
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+    }
+
+    // The key decides the proof system, and the curve and input count
+    // when it records them
+    let record = match vks::fetch(&state, &registration.vk_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("No verification key registered as {}", registration.vk_id),
+            )
+        }
+        Err(e) => {
+            log::error!("Error reading verification key {}: {}", registration.vk_id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read verification key");
+        }
+    };
+    let info = BASE64.decode(&record.verification_key)
+        .ok()
+        .and_then(|vk| verify::key_info(&record.proof_system, &vk));
+    let curve = match (registration.curve.as_deref(), info) {
+        (Some(curve), Some(info)) if curve != info.curve => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Verification key is for {}, not {}", info.curve, curve),
+            )
+        }
+        (Some(curve), _) if !verify::curves(&record.proof_system).contains(&curve) => {
+            let supported = verify::curves(&record.proof_system);
+            let message = if supported.is_empty() {
+                format!("No verifier for {}, so no curve can be checked", record.proof_system)
+            } else {
+                format!(
+                    "Unsupported curve {:?} for {}; use {}",
+                    curve,
+                    record.proof_system,
+                    supported.join(" or ")
+                )
+            };
+            return error_response(StatusCode::BAD_REQUEST, &message);
+        }
+        (Some(curve), _) => curve,
+        (None, Some(info)) => info.curve,
+        (None, None) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("curve is required for {} keys", record.proof_system),
+            )
+        }
+    };
+    if let Some(expected) = info.and_then(|info| info.inputs) {
+        if expected != registration.inputs.len() {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "Verification key expects {} public inputs, schema has {}",
+                    expected,
+                    registration.inputs.len()
+                ),
+            );
+        }
+    }
+
+    let mut poke_slab = NounSlab::new();
+
+    // [%register-circuit name=@t system=@tas curve=@tas vk-id=@t
+    //  inputs=(list [name=@t type=@t]) owner=@t]
+    let name = string_to_cord(&mut poke_slab, &registration.name);
+    let system = string_to_cord(&mut poke_slab, &record.proof_system);
+    let curve = string_to_cord(&mut poke_slab, curve);
+    let vk_id = string_to_cord(&mut poke_slab, &registration.vk_id);
+    let mut inputs = D(0);
+    for spec in registration.inputs.iter().rev() {
+        let input_name = string_to_cord(&mut poke_slab, spec.name.trim());
+        let kind = string_to_cord(&mut poke_slab, &spec.kind);
+        let input = T(&mut poke_slab, &[input_name, kind]);
+        inputs = T(&mut poke_slab, &[input, inputs]);
+    }
+    let owner = string_to_cord(&mut poke_slab, &owner);
+    let cause = T(&mut poke_slab, &[
+        D(b"register-circuit" as &[u8]),
+        name,
+        system,
+        curve,
+        vk_id,
+        inputs,
+        owner,
+    ]);
+    poke_slab.set_root(cause);
+
+    let effects = match poke_kernel(&state, poke_slab, "Failed to register circuit").await {
+        Ok(effects) => effects,
+        Err(response) => return response,
+    };
+    handle_effects(effects).unwrap_or_else(|| {
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "No response from kernel")
+    })
+}
+
+/// List circuits, oldest first
+pub async fn list(State(state): State<SharedState>) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"circuits" as &[u8]), D(0)]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Not found").await
+}
+
+/// Get a circuit by name
+pub async fn get(State(state): State<SharedState>, AxumPath(name): AxumPath<String>) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let name = string_to_cord(&mut peek_slab, &name);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"circuit" as &[u8]), name, D(0)]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Circuit not found").await
+}
+
+/// Look up a circuit
+pub async fn fetch(state: &SharedState, name: &str) -> Result<Option<CircuitRecord>, String> {
+    let mut peek_slab = NounSlab::new();
+    let name = string_to_cord(&mut peek_slab, name);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"circuit" as &[u8]), name, D(0)]);
+    peek_slab.set_root(path);
+
+    let Some(body) = peek_found(state, peek_slab).await? else {
+        return Ok(None);
+    };
+    cord_to_string(body)
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .map(Some)
+        .ok_or_else(|| "Invalid response from kernel".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit(kinds: &[&str]) -> CircuitRecord {
+        CircuitRecord {
+            name: "test".to_string(),
+            proof_system: "groth16".to_string(),
+            vk_id: String::new(),
+            inputs: kinds
+                .iter()
+                .enumerate()
+                .map(|(i, kind)| InputSpec { name: format!("x{}", i), kind: kind.to_string() })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn inputs_follow_the_schema() {
+        let inputs = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert!(check_inputs(&circuit(&["bool", "u8", "field"]), &inputs(&["1", "255", "7"])).is_ok());
+        assert!(check_inputs(&circuit(&["bool"]), &inputs(&["2"])).is_err());
+        assert!(check_inputs(&circuit(&["u8"]), &inputs(&["256"])).is_err());
+        assert!(check_inputs(&circuit(&["u8"]), &inputs(&["1", "2"])).is_err());
+    }
+
+    #[test]
+    fn unreadable_stored_types_are_refused() {
+        let result = check_inputs(&circuit(&["u999"]), &["1".to_string()]);
+        assert!(result.unwrap_err().contains("unreadable type"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Server configuration
+//!
+//! Settings are layered: built-in defaults, then `nockapp.toml`, then
+//! environment variables, then command-line flags.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+
+const DEFAULT_CONFIG: &str = "nockapp.toml";
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_KERNEL: &str = "prover/out.jam";
+const DEFAULT_WEB_ROOT: &str = "prover/web";
+const DEFAULT_DATA_DIR: &str = ".data.prover";
+const DEFAULT_LOG_LEVEL: &str = "info";
+const DEFAULT_WORKERS: usize = 2;
+const DEFAULT_VERIFY_TIMEOUT: u64 = 120;
+const DEFAULT_QUEUE_SIZE: usize = 256;
+const DEFAULT_WEBHOOK_ATTEMPTS: u32 = 5;
+const DEFAULT_IDEMPOTENCY_WINDOW: u64 = 24 * 60 * 60;
+
+/// Answer to a submission identical to a stored one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// `409 Conflict` naming the stored SNARK
+    #[default]
+    Reject,
+    /// `200 OK` with the stored SNARK's ID, as if it had just been stored
+    Return,
+}
+
+/// Command-line flags, each with an environment variable fallback
+#[derive(Debug, Parser)]
+#[command(name = "prover", version, about = "SNARK submission and tracking server")]
+struct Cli {
+    /// Project config file [default: nockapp.toml if present]
+    #[arg(long, env = "PROVER_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// IP address to bind
+    #[arg(long, env = "PROVER_HOST")]
+    host: Option<String>,
+
+    /// Port to listen on
+    #[arg(long, env = "PROVER_PORT")]
+    port: Option<u16>,
+
+    /// Compiled Hoon kernel
+    #[arg(long, env = "PROVER_KERNEL")]
+    kernel: Option<PathBuf>,
+
+    /// Directory of static web files
+    #[arg(long, env = "PROVER_WEB_ROOT")]
+    web_root: Option<PathBuf>,
+
+    /// Directory for kernel state snapshots
+    #[arg(long, env = "PROVER_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Log filter, e.g. `info` or `debug,gnort=off`
+    #[arg(long, env = "RUST_LOG")]
+    log_level: Option<String>,
+
+    /// Proofs verified concurrently
+    #[arg(long, env = "PROVER_WORKERS")]
+    workers: Option<usize>,
+
+    /// Seconds before a verification is abandoned with status `error`
+    #[arg(long, env = "PROVER_VERIFY_TIMEOUT")]
+    verify_timeout: Option<u64>,
+
+    /// Submissions waiting for verification before new ones are refused
+    #[arg(long, env = "PROVER_QUEUE_SIZE")]
+    queue_size: Option<usize>,
+
+    /// URL notified of every finished verification
+    #[arg(long, env = "PROVER_WEBHOOK_URL")]
+    webhook_url: Option<String>,
+
+    /// Key for the HMAC-SHA256 signature on webhook requests
+    #[arg(long, env = "PROVER_WEBHOOK_SECRET", hide_env_values = true)]
+    webhook_secret: Option<String>,
+
+    /// Webhook host allowed to be a private or local address; repeatable
+    #[arg(long = "webhook-allow-host", env = "PROVER_WEBHOOK_ALLOW_HOSTS", value_delimiter = ',')]
+    webhook_allow_hosts: Vec<String>,
+
+    /// Tries per webhook delivery before giving up
+    #[arg(long, env = "PROVER_WEBHOOK_ATTEMPTS")]
+    webhook_attempts: Option<u32>,
+
+    /// Answer to a duplicate submission: `reject` (409) or `return` (200)
+    #[arg(long, env = "PROVER_ON_DUPLICATE", value_enum)]
+    on_duplicate: Option<DuplicatePolicy>,
+
+    /// Seconds a response is replayed to retries with the same
+    /// Idempotency-Key
+    #[arg(long, env = "PROVER_IDEMPOTENCY_WINDOW")]
+    idempotency_window: Option<u64>,
+
+    /// API key with the admin role; setting it turns on authentication
+    #[arg(long, env = "PROVER_ADMIN_KEY", hide_env_values = true)]
+    admin_key: Option<String>,
+
+    /// Restore a state snapshot into the kernel, check every SNARK survives
+    /// the upgrade, and exit without serving
+    #[arg(long, value_name = "SNAPSHOT")]
+    check_snapshot: Option<PathBuf>,
+}
+
+/// `nockapp.toml`
+#[derive(Debug, Default, Deserialize)]
+struct ProjectFile {
+    #[serde(default)]
+    build: BuildSection,
+    #[serde(default)]
+    runtime: RuntimeSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BuildSection {
+    output_jam: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RuntimeSection {
+    host: Option<String>,
+    port: Option<u16>,
+    web_root: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    log_level: Option<String>,
+    workers: Option<usize>,
+    verify_timeout: Option<u64>,
+    queue_size: Option<usize>,
+    webhook_url: Option<String>,
+    webhook_secret: Option<String>,
+    webhook_allow_hosts: Option<Vec<String>>,
+    webhook_attempts: Option<u32>,
+    on_duplicate: Option<DuplicatePolicy>,
+    idempotency_window: Option<u64>,
+    admin_key: Option<String>,
+}
+
+/// Validated server configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub kernel: PathBuf,
+    pub web_root: PathBuf,
+    pub data_dir: PathBuf,
+    pub log_level: String,
+    pub workers: usize,
+    pub verify_timeout: Duration,
+    pub queue_size: usize,
+    pub webhook_url: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub webhook_allow_hosts: Vec<String>,
+    pub webhook_attempts: u32,
+    pub on_duplicate: DuplicatePolicy,
+    pub idempotency_window: Duration,
+    pub admin_key: Option<String>,
+    pub check_snapshot: Option<PathBuf>,
+}
+
+impl Config {
+    /// Load configuration from the command line, environment and config file
+    pub fn load() -> Result<Self> {
+        let cli = Cli::parse();
+        let file = match &cli.config {
+            Some(path) => read_project_file(path)?,
+            None if Path::new(DEFAULT_CONFIG).exists() => read_project_file(Path::new(DEFAULT_CONFIG))?,
+            None => ProjectFile::default(),
+        };
+
+        let host = cli
+            .host
+            .or(file.runtime.host)
+            .unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let ip: IpAddr = host
+            .parse()
+            .with_context(|| format!("Invalid host {:?}: expected an IP address", host))?;
+        let port = cli.port.or(file.runtime.port).unwrap_or(DEFAULT_PORT);
+
+        let config = Config {
+            bind: SocketAddr::new(ip, port),
+            kernel: cli
+                .kernel
+                .or(file.build.output_jam)
+                .unwrap_or_else(|| DEFAULT_KERNEL.into()),
+            web_root: cli
+                .web_root
+                .or(file.runtime.web_root)
+                .unwrap_or_else(|| DEFAULT_WEB_ROOT.into()),
+            data_dir: cli
+                .data_dir
+                .or(file.runtime.data_dir)
+                .unwrap_or_else(|| DEFAULT_DATA_DIR.into()),
+            log_level: cli
+                .log_level
+                .or(file.runtime.log_level)
+                .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
+            workers: cli.workers.or(file.runtime.workers).unwrap_or(DEFAULT_WORKERS),
+            verify_timeout: Duration::from_secs(
+                cli.verify_timeout
+                    .or(file.runtime.verify_timeout)
+                    .unwrap_or(DEFAULT_VERIFY_TIMEOUT),
+            ),
+            queue_size: cli.queue_size.or(file.runtime.queue_size).unwrap_or(DEFAULT_QUEUE_SIZE),
+            webhook_url: cli.webhook_url.or(file.runtime.webhook_url),
+            webhook_secret: cli.webhook_secret.or(file.runtime.webhook_secret),
+            webhook_allow_hosts: Some(cli.webhook_allow_hosts)
+                .filter(|hosts| !hosts.is_empty())
+                .or(file.runtime.webhook_allow_hosts)
+                .unwrap_or_default(),
+            webhook_attempts: cli
+                .webhook_attempts
+                .or(file.runtime.webhook_attempts)
+                .unwrap_or(DEFAULT_WEBHOOK_ATTEMPTS),
+            on_duplicate: cli.on_duplicate.or(file.runtime.on_duplicate).unwrap_or_default(),
+            idempotency_window: Duration::from_secs(
+                cli.idempotency_window
+                    .or(file.runtime.idempotency_window)
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW),
+            ),
+            admin_key: cli.admin_key.or(file.runtime.admin_key),
+            check_snapshot: cli.check_snapshot,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.kernel.is_file() {
+            bail!(
+                "Kernel not found at {:?}; run 'nockup project build' first or pass --kernel",
+                self.kernel
+            );
+        }
+        if !self.web_root.is_dir() {
+            bail!("Web root {:?} is not a directory; pass --web-root", self.web_root);
+        }
+        if self.data_dir.exists() && !self.data_dir.is_dir() {
+            bail!("Data directory {:?} exists but is not a directory", self.data_dir);
+        }
+        if self.workers == 0 {
+            bail!("workers must be at least 1");
+        }
+        if self.verify_timeout.is_zero() {
+            bail!("verify_timeout must be at least 1 second");
+        }
+        if self.queue_size == 0 {
+            bail!("queue_size must be at least 1");
+        }
+        if let Some(url) = &self.webhook_url {
+            if let Err(e) = crate::webhook::check_url(url, &self.webhook_allow_hosts) {
+                bail!("Invalid webhook_url {:?}: {}", url, e);
+            }
+            if self.webhook_secret.is_none() {
+                bail!("webhook_url needs a webhook_secret to sign requests with");
+            }
+        }
+        if self.webhook_attempts == 0 {
+            bail!("webhook_attempts must be at least 1");
+        }
+        if self.idempotency_window.is_zero() {
+            bail!("idempotency_window must be at least 1 second");
+        }
+        if let Some(key) = &self.admin_key {
+            if key.len() < crate::auth::MIN_ADMIN_KEY_LEN {
+                bail!(
+                    "admin_key must be at least {} characters",
+                    crate::auth::MIN_ADMIN_KEY_LEN
+                );
+            }
+        }
+        if let Some(path) = &self.check_snapshot {
+            if !path.is_file() {
+                bail!("Snapshot {:?} not found", path);
+            }
+        }
+        check_log_level(&self.log_level)?;
+        Ok(())
+    }
+}
+
+/// Check every directive of a log filter names a valid level
+///
+/// env_logger reads a bare word that is not a level as a module to trace,
+/// so a typo such as `verbose` would silence everything else; modules must
+/// be given as `module=level`.
+fn check_log_level(log_level: &str) -> Result<()> {
+    let filters = log_level.split('/').next().unwrap_or_default();
+    for directive in filters.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let level = match directive.split_once('=') {
+            Some((_, level)) => level,
+            None => directive,
+        };
+        if level.parse::<log::LevelFilter>().is_err() {
+            bail!(
+                "Invalid log level {:?} in {:?}: use off, error, warn, info, debug or \
+                 trace, optionally as module=level",
+                level,
+                log_level
+            );
+        }
+    }
+    Ok(())
+}
+
+fn read_project_file(path: &Path) -> Result<ProjectFile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {:?}", path))?;
+    toml::from_str(&text).with_context(|| format!("Invalid config file {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_levels() {
+        for ok in ["info", "debug,gnort=off", "prover=trace, warn", "INFO", "info/foo"] {
+            assert!(check_log_level(ok).is_ok(), "{:?} should be accepted", ok);
+        }
+        for bad in ["verbose", "prover=loud", "info,chatty"] {
+            assert!(check_log_level(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+}
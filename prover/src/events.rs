@@ -0,0 +1,145 @@
+//! Live SNARK events
+//!
+//! The kernel emits `[%event name=@tas id=@ud data=@t]` alongside every
+//! submission, status change and deletion. The driver broadcasts them to
+//! subscribers, such as the Server-Sent Events stream at `/api/v1/events`.
+//! Like the rest of the read API, the stream carries every SNARK's events
+//! to any caller with a valid key.
+
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::response::sse::{self, KeepAlive, Sse};
+use nockapp::noun::Noun;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{cord_to_string, SharedState};
+
+/// Events buffered per subscriber before it starts missing them
+pub const EVENT_BUFFER: usize = 256;
+
+/// A change to one SNARK
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// `submitted`, `status` or `deleted`
+    pub name: String,
+    pub id: u64,
+    /// JSON rendered by the kernel
+    pub data: String,
+}
+
+/// Parse a `[%event name=@tas id=@ud data=@t]` effect
+pub fn parse(effect: Noun) -> Option<Event> {
+    let cell = effect.as_cell().ok()?;
+    if !cell.head().eq_bytes(b"event") {
+        return None;
+    }
+    let fields = cell.tail().as_cell().ok()?;
+    let rest = fields.tail().as_cell().ok()?;
+    Some(Event {
+        name: cord_to_string(fields.head())?,
+        id: rest.head().as_atom().ok()?.as_u64().ok()?,
+        data: cord_to_string(rest.tail())?,
+    })
+}
+
+/// Broadcast the events among a poke's effects
+pub fn publish<'a>(events: &broadcast::Sender<Event>, effects: impl IntoIterator<Item = &'a Noun>) {
+    for event in effects.into_iter().filter_map(|&effect| parse(effect)) {
+        log::debug!("Event {} for SNARK #{}", event.name, event.id);
+        // No subscribers is not an error
+        let _ = events.send(event);
+    }
+}
+
+/// Stream SNARK events as Server-Sent Events
+///
+/// A subscriber that falls too far behind receives a `lagged` event with the
+/// number of events it missed, and should reload what it displays.
+pub async fn stream(
+    State(state): State<SharedState>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let events = BroadcastStream::new(state.events.subscribe()).map(|event| {
+        Ok(match event {
+            Ok(event) => sse::Event::default().event(event.name).data(event.data),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                sse::Event::default().event("lagged").data(missed.to_string())
+            }
+        })
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::extract::{Path, Query};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::testing;
+    use crate::{delete_snark, DeleteParams};
+
+    /// Read the next Server-Sent Event as its name and data
+    async fn next(body: &mut (impl Stream<Item = Result<bytes::Bytes, axum::Error>> + Unpin)) -> (String, String) {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("event arrives")
+            .expect("stream stays open")
+            .expect("stream is readable");
+        let text = String::from_utf8(chunk.to_vec()).expect("events are UTF-8");
+        let field = |name: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap_or_else(|| panic!("no {} in {:?}", name, text))
+                .to_string()
+        };
+        (field("event: "), field("data: "))
+    }
+
+    async fn delete(state: &SharedState, id: u64) {
+        let params = DeleteParams { actor: Some("alice".to_string()) };
+        let response = delete_snark(State(state.clone()), None, Path(id), Query(params)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn streams_events_in_order() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let ids = {
+            let mut app = state.nockapp.write().await;
+            [testing::submit(&mut app, "alice", "", "a").await, testing::submit(&mut app, "alice", "", "b").await]
+        };
+        let mut body = stream(State(state.clone())).await.into_response().into_body().into_data_stream();
+
+        for &id in &ids {
+            delete(&state, id).await;
+        }
+        for id in ids {
+            let (name, data) = next(&mut body).await;
+            assert_eq!(name, "deleted");
+            let data: Value = serde_json::from_str(&data).expect("data is JSON");
+            assert_eq!(data["id"], id);
+            assert_eq!(data["actor"], "alice");
+        }
+    }
+
+    #[tokio::test]
+    async fn lagging_subscribers_are_told_what_they_missed() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let mut body = stream(State(state.clone())).await.into_response().into_body().into_data_stream();
+
+        for id in 0..EVENT_BUFFER as u64 + 3 {
+            let event = Event { name: "status".to_string(), id, data: "{}".to_string() };
+            state.events.send(event).expect("the stream is subscribed");
+        }
+        assert_eq!(next(&mut body).await, ("lagged".to_string(), "3".to_string()));
+        assert_eq!(next(&mut body).await, ("status".to_string(), "{}".to_string()));
+    }
+}
@@ -0,0 +1,194 @@
+//! Idempotent submissions
+//!
+//! `POST /api/v1/snark` honours an `Idempotency-Key` header. The kernel saves
+//! its answer to the first request made with a key, and for the configured
+//! window a retry with the same key and body gets that answer again, marked
+//! `Idempotent-Replayed: true`. Reusing a key for a different request is
+//! refused with 422. Requests refused before they reach the kernel, such as
+//! invalid ones, are not saved and are checked afresh on retry.
+//!
+//! Keys belong to the caller: the kernel stores them under the principal
+//! with authentication on, or the submitter without, so two clients that
+//! pick the same key neither see nor block each other's answers.
+
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{Noun, D, T};
+use sha2::{Digest, Sha256};
+
+use crate::auth::Principal;
+use crate::{cord_to_string, error_response, peek_found, string_to_cord, unit, SharedState, SnarkSubmission};
+
+const HEADER: &str = "idempotency-key";
+
+/// Longest key accepted
+const MAX_KEY_LEN: usize = 255;
+
+/// A key and the request it came with
+pub struct Request {
+    /// As the client sent it
+    key: String,
+    /// The key as stored in the kernel, prefixed with its owner
+    scoped: String,
+    /// Hex SHA-256 of the submission
+    hash: String,
+}
+
+/// An answer saved by the kernel
+struct Saved {
+    hash: String,
+    code: u16,
+    body: String,
+}
+
+impl Request {
+    /// Read the key from the headers, if the client sent one
+    ///
+    /// The key is scoped to `principal`, or without authentication to the
+    /// submitter. A malformed key is an error message for a 400.
+    pub fn from_headers(
+        headers: &HeaderMap,
+        principal: Option<&Principal>,
+        submission: &SnarkSubmission,
+    ) -> Result<Option<Self>, String> {
+        let Some(value) = headers.get(HEADER) else {
+            return Ok(None);
+        };
+        let key = value.as_bytes();
+        if key.is_empty() || key.len() > MAX_KEY_LEN || !key.iter().all(u8::is_ascii_graphic) {
+            return Err(format!("Idempotency-Key must be 1 to {} visible ASCII characters", MAX_KEY_LEN));
+        }
+        let key = String::from_utf8_lossy(key).into_owned();
+        let owner = principal.map_or(submission.submitter.as_str(), |p| p.name.as_str());
+        // Length-prefixed, so no owner and key can be mistaken for another
+        let scoped = format!("{}:{}:{}", owner.len(), owner, key);
+        let body = serde_json::to_vec(submission).unwrap_or_default();
+        let hash = hex::encode(Sha256::digest(&body));
+        Ok(Some(Request { key, scoped, hash }))
+    }
+
+    /// The saved answer for this key, if there is one
+    ///
+    /// The kernel checks again when the submission is poked, so two retries
+    /// racing past this point still get one answer.
+    pub async fn replay(&self, state: &SharedState) -> Option<Response> {
+        let saved = match lookup(state, &self.scoped).await {
+            Ok(saved) => saved?,
+            Err(e) => {
+                log::error!("Error reading Idempotency-Key {:?}: {}", self.key, e);
+                return Some(error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to read Idempotency-Key",
+                ));
+            }
+        };
+        if saved.hash != self.hash {
+            return Some(error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key was used for a different request",
+            ));
+        }
+        let status = StatusCode::from_u16(saved.code).unwrap_or(StatusCode::OK);
+        Some(
+            (
+                status,
+                [
+                    (header::CONTENT_TYPE, "application/json"),
+                    (header::HeaderName::from_static("idempotent-replayed"), "true"),
+                ],
+                saved.body,
+            )
+                .into_response(),
+        )
+    }
+}
+
+/// `(unit [key=@t request=@t window=@ud])` for the submit poke
+pub fn to_noun(request: Option<&Request>, slab: &mut NounSlab, window: Duration) -> Noun {
+    let value = request.map(|request| {
+        let key = string_to_cord(slab, &request.scoped);
+        let hash = string_to_cord(slab, &request.hash);
+        T(slab, &[key, hash, D(window.as_secs())])
+    });
+    unit(slab, value)
+}
+
+/// Peek the saved `[request=@t code=@ud body=@t]` for a key
+async fn lookup(state: &SharedState, key: &str) -> Result<Option<Saved>, String> {
+    let mut peek_slab = NounSlab::new();
+    let key = string_to_cord(&mut peek_slab, key);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"response" as &[u8]), key, D(0)]);
+    peek_slab.set_root(path);
+
+    let Some(saved) = peek_found(state, peek_slab).await? else {
+        return Ok(None);
+    };
+    parse(saved).map(Some).ok_or_else(|| "Invalid response from kernel".to_string())
+}
+
+fn parse(saved: Noun) -> Option<Saved> {
+    let cell = saved.as_cell().ok()?;
+    let rest = cell.tail().as_cell().ok()?;
+    Some(Saved {
+        hash: cord_to_string(cell.head())?,
+        code: u16::try_from(rest.head().as_atom().ok()?.as_u64().ok()?).ok()?,
+        body: cord_to_string(rest.tail())?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Role;
+
+    fn request(principal: Option<&Principal>, submitter: &str) -> Request {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER, "retry-1".parse().unwrap());
+        let submission: SnarkSubmission = serde_json::from_value(serde_json::json!({
+            "proof": "cHJvb2Y=",
+            "public_inputs": ["1"],
+            "proof_system": "groth16",
+            "submitter": submitter,
+        }))
+        .unwrap();
+        Request::from_headers(&headers, principal, &submission)
+            .ok()
+            .flatten()
+            .expect("key was sent")
+    }
+
+    #[test]
+    fn keys_are_visible_ascii() {
+        let submission: SnarkSubmission = serde_json::from_value(serde_json::json!({
+            "proof": "cHJvb2Y=",
+            "public_inputs": ["1"],
+            "proof_system": "groth16",
+            "submitter": "alice",
+        }))
+        .unwrap();
+        let read = |key: &[u8]| {
+            let mut headers = HeaderMap::new();
+            headers.insert(HEADER, header::HeaderValue::from_bytes(key).unwrap());
+            Request::from_headers(&headers, None, &submission).map(|request| request.map(|r| r.key))
+        };
+        assert_eq!(read(b"retry-1~!").unwrap().as_deref(), Some("retry-1~!"));
+        assert!(read(&[b'k'; MAX_KEY_LEN]).is_ok());
+        for bad in [&b""[..], b"retry 1", b"retry\t1", b"caf\xc3\xa9", &[b'k'; MAX_KEY_LEN + 1]] {
+            assert!(read(bad).is_err(), "{:?} accepted", String::from_utf8_lossy(bad));
+        }
+        assert!(Request::from_headers(&HeaderMap::new(), None, &submission).unwrap().is_none());
+    }
+
+    #[test]
+    fn keys_are_scoped_to_their_owner() {
+        let alice = Principal { name: "alice".to_string(), role: Role::User };
+        let bob = Principal { name: "bob".to_string(), role: Role::User };
+        assert_ne!(request(Some(&alice), "alice").scoped, request(Some(&bob), "bob").scoped);
+        assert_eq!(request(Some(&alice), "alice").scoped, request(Some(&alice), "carol").scoped);
+        assert_ne!(request(None, "alice").scoped, request(None, "bob").scoped);
+        assert_eq!(request(Some(&alice), "alice").key, "retry-1");
+    }
+}
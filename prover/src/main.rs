@@ -4,25 +4,47 @@
 
 use std::error::Error;
 use std::fs;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
-    extract::{Path as AxumPath, State},
-    http::{StatusCode, header},
+    extract::{Path as AxumPath, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware,
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
-    Json, Router,
+    routing::{delete, get, patch, post},
+    Extension, Json, Router,
 };
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use sha2::{Digest, Sha256};
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tower_http::services::ServeDir;
 
-use nockapp::driver::{make_driver, IODriverFn, NockAppHandle, Operation};
 use nockapp::kernel::boot;
 use nockapp::noun::slab::NounSlab;
-use nockapp::noun::{Noun, D, T};
+use nockapp::noun::{IndirectAtom, Noun, D, T};
+use nockapp::NockApp;
+
+mod auth;
+mod check;
+mod circuits;
+mod config;
+mod events;
+mod idempotency;
+mod persist;
+#[cfg(test)]
+mod testing;
+mod verify;
+mod vks;
+mod webhook;
+mod worker;
+mod ws;
+
+use config::{Config, DuplicatePolicy};
+use persist::Store;
 
 // ============================================================================
 // Type Definitions
@@ -31,19 +53,50 @@ use nockapp::noun::{Noun, D, T};
 /// SNARK submission request
 #[derive(Debug, Serialize, Deserialize)]
 struct SnarkSubmission {
-    proof: String,
-    public_inputs: Vec,
-    verification_key: String,
+    proof: Encoded,
+    public_inputs: Vec<String>,
+    /// Inline key; give this or `vk_id`
+    verification_key: Option<Encoded>,
+    /// ID of a registered key
+    vk_id: Option<String>,
+    /// Registered circuit, which supplies the key and input schema
+    circuit: Option<String>,
+    /// May be left out when a circuit is named
+    #[serde(default)]
     proof_system: String,
+    /// `auto` (default), `arkworks`, `gnark` or `snarkjs`
+    format: Option<String>,
+    /// Defaults to the caller's principal when authentication is on
+    #[serde(default)]
     submitter: String,
-    notes: Option,
+    notes: Option<String>,
+    /// Notified when verification finishes
+    callback_url: Option<String>,
+}
+
+/// Proof or verification key as submitted: Base64 bytes, or a JSON
+/// document (snarkjs) given inline or as a string
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Encoded {
+    Text(String),
+    Json(serde_json::Value),
+}
+
+impl Encoded {
+    fn is_empty(&self) -> bool {
+        match self {
+            Encoded::Text(text) => text.trim().is_empty(),
+            Encoded::Json(value) => value.is_null(),
+        }
+    }
 }
 
 /// SNARK submission response
 #[derive(Debug, Serialize, Deserialize)]
 struct SnarkResponse {
     success: bool,
-    id: Option,
+    id: Option<u64>,
     message: String,
 }
 
@@ -52,21 +105,67 @@ struct SnarkResponse {
 struct SnarkDetails {
     id: u64,
     proof: String,
-    public_inputs: Vec,
+    public_inputs: Vec<String>,
     verification_key: String,
+    /// Registered key the SNARK is linked to
+    vk_id: Option<String>,
+    /// Circuit the SNARK was submitted against
+    circuit: Option<String>,
     proof_system: String,
     submitter: String,
+    /// ISO-8601 UTC timestamp
     submitted: String,
     status: String,
-    error_message: Option,
+    error_message: Option<String>,
     notes: String,
 }
 
 /// List of SNARKs response
 #[derive(Debug, Serialize, Deserialize)]
 struct SnarkList {
-    snarks: Vec,
+    snarks: Vec<SnarkSummary>,
+    /// Number of entries matching the filters, across all pages
     total: usize,
+    /// Cursor for the next page, if there is one
+    next_cursor: Option<String>,
+}
+
+/// Status change request
+#[derive(Debug, Deserialize)]
+struct StatusUpdate {
+    status: String,
+    /// Who is making the change; defaults to the caller's principal
+    #[serde(default)]
+    actor: String,
+    /// Why; stored as the error message for `failed` and `error`
+    reason: Option<String>,
+}
+
+/// Query parameters for the delete endpoint
+#[derive(Debug, Default, Deserialize)]
+struct DeleteParams {
+    /// Who is deleting; defaults to the caller's principal
+    actor: Option<String>,
+}
+
+/// Query parameters for the list endpoint
+#[derive(Debug, Default, Deserialize)]
+struct ListParams {
+    limit: Option<u64>,
+    /// `next_cursor` from the previous page
+    cursor: Option<String>,
+    status: Option<String>,
+    proof_system: Option<String>,
+    submitter: Option<String>,
+    circuit: Option<String>,
+    /// Earliest submission time, inclusive (RFC 3339 or YYYY-MM-DD)
+    from: Option<String>,
+    /// Latest submission time, exclusive (RFC 3339 or YYYY-MM-DD)
+    to: Option<String>,
+    /// `id` or `submitted`
+    sort: Option<String>,
+    /// `asc` or `desc`
+    order: Option<String>,
 }
 
 /// Summary of a SNARK for list view
@@ -77,6 +176,7 @@ struct SnarkSummary {
     submitter: String,
     submitted: String,
     status: String,
+    error_message: Option<String>,
     notes: String,
 }
 
@@ -86,8 +186,43 @@ struct ErrorResponse {
     error: String,
 }
 
-// Shared state for NockApp handle
-type SharedState = Arc<RwLock>;
+/// Page size when `limit` is not given
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// Largest page a client may request
+const MAX_PAGE_SIZE: u64 = 500;
+
+/// Actor recorded for status changes made by the built-in verifier
+const VERIFIER_ACTOR: &str = "verifier";
+
+/// Actor recorded for deletions that name nobody with authentication off
+const ANONYMOUS_ACTOR: &str = "anonymous";
+
+/// Statuses a SNARK can be in
+const STATUSES: [&str; 5] = ["pending", "verifying", "verified", "failed", "error"];
+
+/// Verdicts that cannot be changed once set
+const FINAL_STATUSES: [&str; 2] = ["verified", "failed"];
+
+/// State shared by the HTTP handlers
+struct AppState {
+    nockapp: RwLock<NockApp>,
+    store: Store,
+    /// IDs of SNARKs waiting for verification
+    jobs: mpsc::Sender<u64>,
+    /// Submissions, status changes and deletions, as they happen
+    events: broadcast::Sender<events::Event>,
+    /// Finished verifications waiting for webhook delivery
+    completions: mpsc::UnboundedSender<webhook::Completion>,
+    webhook: webhook::Settings,
+    on_duplicate: DuplicatePolicy,
+    /// How long answers are kept for Idempotency-Key retries
+    idempotency_window: Duration,
+    /// SHA-256 of the configured admin key; authentication is off without one
+    admin_key_hash: Option<String>,
+}
+
+type SharedState = Arc<AppState>;
 
 // ============================================================================
 // HTTP Handlers
@@ -95,41 +230,185 @@ type SharedState = Arc<RwLock>;
 
 /// Handle SNARK submission
 async fn submit_snark(
-    State(nockapp): State,
-    Json(submission): Json,
+    State(state): State<SharedState>,
+    principal: Option<Extension<auth::Principal>>,
+    headers: HeaderMap,
+    Json(mut submission): Json<SnarkSubmission>,
 ) -> Response {
+    // Authenticated callers submit as themselves
+    submission.submitter = match auth::act_as(principal.as_deref(), &submission.submitter, "submitter") {
+        Ok(submitter) => submitter,
+        Err(forbidden) => return forbidden.into_response(),
+    };
+
+    // Retries with an Idempotency-Key get the first answer; keys are
+    // scoped to the caller, so clients that pick the same key stay apart
+    let idempotent = match idempotency::Request::from_headers(&headers, principal.as_deref(), &submission) {
+        Ok(idempotent) => idempotent,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    if let Some(request) = &idempotent {
+        if let Some(response) = request.replay(&state).await {
+            return response;
+        }
+    }
+
     // Validate input
     if submission.proof.is_empty() {
         return error_response(StatusCode::BAD_REQUEST, "Proof data is required");
     }
-    if submission.verification_key.is_empty() {
-        return error_response(StatusCode::BAD_REQUEST, "Verification key is required");
-    }
     if submission.submitter.is_empty() {
         return error_response(StatusCode::BAD_REQUEST, "Submitter is required");
     }
 
-    // Validate Base64 encoding
-    if base64::decode(&submission.proof).is_err() {
-        return error_response(StatusCode::BAD_REQUEST, "Invalid Base64 in proof data");
+    // A circuit fixes the proof system and key
+    let circuit = match &submission.circuit {
+        Some(name) => match circuits::fetch(&state, name).await {
+            Ok(Some(circuit)) => Some(circuit),
+            Ok(None) => {
+                return error_response(StatusCode::BAD_REQUEST, &format!("Unknown circuit {:?}", name))
+            }
+            Err(e) => {
+                log::error!("Error reading circuit {}: {}", name, e);
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read circuit");
+            }
+        },
+        None => None,
+    };
+    let proof_system = match (&circuit, submission.proof_system.as_str()) {
+        (Some(circuit), "") => circuit.proof_system.clone(),
+        (Some(circuit), system) if system != circuit.proof_system => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Circuit {} uses {}, not {}", circuit.name, circuit.proof_system, system),
+            )
+        }
+        (None, "") => return error_response(StatusCode::BAD_REQUEST, "Proof system is required"),
+        (_, system) => system.to_string(),
+    };
+    let inline_vk = submission.verification_key.as_ref().filter(|vk| !vk.is_empty());
+    let vk_id = match &circuit {
+        Some(circuit) => {
+            let other_key = inline_vk.is_some()
+                || submission.vk_id.as_ref().is_some_and(|id| *id != circuit.vk_id);
+            if other_key {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Circuit {} has its own verification key", circuit.name),
+                );
+            }
+            Some(&circuit.vk_id)
+        }
+        None => submission.vk_id.as_ref(),
+    };
+
+    // Decode and convert to the encoding the verifier reads
+    let format = match submission.format.as_deref().unwrap_or("auto").parse::<verify::Format>() {
+        Ok(format) => format,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    let Some(proof_blob) = decode_blob(&submission.proof) else {
+        return error_response(StatusCode::BAD_REQUEST, "Invalid proof data: not Base64 or JSON");
+    };
+    let proof_bytes = match verify::normalize_proof(&proof_system, format, proof_blob) {
+        Ok(proof) => proof,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let vk_bytes = match (inline_vk, vk_id) {
+        (Some(_), Some(_)) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Give either verification_key or vk_id, not both",
+            )
+        }
+        (Some(vk), None) => {
+            let Some(vk_blob) = decode_blob(vk) else {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid verification key: not Base64 or JSON",
+                );
+            };
+            match verify::normalize_vk(&proof_system, format, vk_blob) {
+                Ok(vk) => vk,
+                Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+            }
+        }
+        (None, Some(vk_id)) => match registered_vk(&state, vk_id, &proof_system).await {
+            Ok(vk) => vk,
+            Err(response) => return response,
+        },
+        (None, None) => {
+            return error_response(StatusCode::BAD_REQUEST, "Verification key is required")
+        }
+    };
+    if let Some(circuit) = &circuit {
+        if let Err(message) = circuits::check_inputs(circuit, &submission.public_inputs) {
+            return error_response(StatusCode::UNPROCESSABLE_ENTITY, &message);
+        }
     }
-    if base64::decode(&submission.verification_key).is_err() {
-        return error_response(StatusCode::BAD_REQUEST, "Invalid Base64 in verification key");
+    if let Err(e) = verify::check_inputs(
+        &proof_system,
+        &proof_bytes,
+        &vk_bytes,
+        &submission.public_inputs,
+    ) {
+        return error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string());
+    }
+    if let Some(url) = &submission.callback_url {
+        if state.webhook.secret.is_none() {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Callbacks are disabled: the server has no webhook secret",
+            );
+        }
+        if let Err(e) = webhook::check_url(url, &state.webhook.allow_hosts) {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid callback_url: {}", e));
+        }
     }
 
+    // Hold a place in the verification queue so the SNARK is never stored
+    // without one
+    let job = if verify::supports(&proof_system) {
+        match state.jobs.try_reserve() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                return error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Verification queue is full, try again later",
+                )
+            }
+        }
+    } else {
+        None
+    };
+
     // Construct poke for Hoon kernel
     let mut poke_slab = NounSlab::new();
-    
+
     // Build %submit-snark cause
-    // [%submit-snark proof=@t inputs=(list @t) vk=@t system=@tas submitter=@t notes=@t]
+    // [%submit-snark proof=@t inputs=(list @t) vk=@t system=@tas submitter=@t notes=@t
+    //  callback=(unit @t) vk-id=@t circuit=(unit @t) digest=@t
+    //  on-duplicate=?(%reject %return) idempotency=(unit [key=@t request=@t window=@ud])]
     let cause_tag = D(b"submit-snark" as &[u8]);
-    let proof = string_to_cord(&mut poke_slab, &submission.proof);
+    let proof = string_to_cord(&mut poke_slab, &BASE64.encode(&proof_bytes));
     let inputs = string_list_to_noun(&mut poke_slab, &submission.public_inputs);
-    let vk = string_to_cord(&mut poke_slab, &submission.verification_key);
-    let system = D(submission.proof_system.as_bytes());
+    let vk = string_to_cord(&mut poke_slab, &BASE64.encode(&vk_bytes));
+    let system = string_to_cord(&mut poke_slab, &proof_system);
     let submitter = string_to_cord(&mut poke_slab, &submission.submitter);
     let notes = string_to_cord(&mut poke_slab, submission.notes.as_deref().unwrap_or(""));
-    
+    let callback = submission.callback_url.as_deref().map(|url| string_to_cord(&mut poke_slab, url));
+    let callback = unit(&mut poke_slab, callback);
+    let vk_id = string_to_cord(&mut poke_slab, &vks::key_id(&vk_bytes));
+    let circuit = circuit.map(|circuit| string_to_cord(&mut poke_slab, &circuit.name));
+    let circuit = unit(&mut poke_slab, circuit);
+    let digest = submission_digest(&proof_system, &proof_bytes, &vk_bytes, &submission.public_inputs);
+    let digest = string_to_cord(&mut poke_slab, &digest);
+    let on_duplicate = match state.on_duplicate {
+        DuplicatePolicy::Reject => D(b"reject" as &[u8]),
+        DuplicatePolicy::Return => D(b"return" as &[u8]),
+    };
+    let idempotent = idempotency::to_noun(idempotent.as_ref(), &mut poke_slab, state.idempotency_window);
+
     let poke_noun = T(&mut poke_slab, &[
         cause_tag,
         proof,
@@ -138,127 +417,548 @@ async fn submit_snark(
         system,
         submitter,
         notes,
+        callback,
+        vk_id,
+        circuit,
+        digest,
+        on_duplicate,
+        idempotent,
     ]);
     poke_slab.set_root(poke_noun);
 
     // Send poke to kernel
-    let mut app = nockapp.write().await;
-    match app.poke(poke_slab).await {
-        Ok(effects) => {
-            // Parse effects for HTTP response
-            for effect in effects {
-                if let Some(response) = parse_http_response(effect) {
-                    return response;
-                }
-            }
-            // Fallback success response
-            success_response(StatusCode::CREATED, "SNARK submitted successfully")
+    let effects = match poke_kernel(&state, poke_slab, "Failed to submit SNARK").await {
+        Ok(effects) => effects,
+        Err(response) => return response,
+    };
+
+    // Parse effects for the new ID and HTTP response
+    let submitted_id = effects.iter().find_map(|&effect| parse_submitted_id(effect));
+    let response = handle_effects(effects);
+
+    // Verify in the background
+    if let (Some(job), Some(id)) = (job, submitted_id) {
+        job.send(id);
+    }
+
+    match response {
+        Some(response) => response,
+        // Fallback success response
+        None => (
+            StatusCode::CREATED,
+            Json(SnarkResponse {
+                success: true,
+                id: submitted_id,
+                message: "SNARK submitted successfully".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Digest identifying a submission
+///
+/// SHA-256 over the proof system, the stored proof and key, and each public
+/// input as a big-endian integer, so `0x10` and `16` are the same input.
+/// Every part is length-prefixed.
+fn submission_digest(proof_system: &str, proof: &[u8], vk: &[u8], inputs: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    let mut part = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_be_bytes());
+        hasher.update(bytes);
+    };
+    part(proof_system.as_bytes());
+    part(proof);
+    part(vk);
+    for input in inputs {
+        match verify::parse_uint(input) {
+            Some(value) => part(&value.to_bytes_be()),
+            None => part(input.as_bytes()),
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Stored encoding of a registered key, checked against the submission
+async fn registered_vk(state: &SharedState, vk_id: &str, proof_system: &str) -> Result<Vec<u8>, Response> {
+    let record = match vks::fetch(state, vk_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("No verification key registered as {}", vk_id),
+            ))
         }
         Err(e) => {
-            log::error!("Error poking kernel: {:?}", e);
-            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to submit SNARK")
+            log::error!("Error reading verification key {}: {}", vk_id, e);
+            return Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read verification key",
+            ));
         }
+    };
+    if record.proof_system != proof_system {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Verification key {} is for {}, not {}",
+                vk_id, record.proof_system, proof_system
+            ),
+        ));
     }
+    BASE64.decode(&record.verification_key).map_err(|_| {
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "Stored verification key is not valid Base64")
+    })
 }
 
 /// Get a specific SNARK by ID
 async fn get_snark(
-    State(nockapp): State,
-    AxumPath(id): AxumPath,
+    State(state): State<SharedState>,
+    AxumPath(id): AxumPath<u64>,
 ) -> Response {
-    let mut poke_slab = NounSlab::new();
-    let cause = T(&mut poke_slab, &[
-        D(b"get-snark" as &[u8]),
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[
+        D(b"x" as &[u8]),
+        D(b"snark" as &[u8]),
         D(id),
+        D(0),
     ]);
-    poke_slab.set_root(cause);
+    peek_slab.set_root(path);
 
-    let mut app = nockapp.write().await;
-    match app.poke(poke_slab).await {
-        Ok(effects) => {
-            for effect in effects {
-                if let Some(response) = parse_http_response(effect) {
-                    return response;
-                }
-            }
-            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Invalid response from kernel")
-        }
+    peek_json(&state, peek_slab, "SNARK not found").await
+}
+
+/// Get the status history of a SNARK, oldest change first
+async fn get_snark_history(
+    State(state): State<SharedState>,
+    AxumPath(id): AxumPath<u64>,
+) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[
+        D(b"x" as &[u8]),
+        D(b"history" as &[u8]),
+        D(id),
+        D(0),
+    ]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "SNARK not found").await
+}
+
+/// Get the webhook delivery log of a SNARK, oldest attempt first
+async fn get_snark_deliveries(
+    State(state): State<SharedState>,
+    AxumPath(id): AxumPath<u64>,
+) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[
+        D(b"x" as &[u8]),
+        D(b"deliveries" as &[u8]),
+        D(id),
+        D(0),
+    ]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "SNARK not found").await
+}
+
+/// List SNARKs matching the query parameters, one page at a time
+async fn list_snarks(
+    State(state): State<SharedState>,
+    Query(params): Query<ListParams>,
+) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let query = match list_query(&mut peek_slab, &params) {
+        Ok(query) => query,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"snarks" as &[u8]), query]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Not found").await
+}
+
+/// Count all stored SNARKs
+async fn count_snarks(State(state): State<SharedState>) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"count" as &[u8]), D(0)]);
+    peek_slab.set_root(path);
+
+    let count = match peek_found(&state, peek_slab).await {
+        Ok(count) => count.and_then(|count| count.as_atom().ok()?.as_u64().ok()),
         Err(e) => {
             log::error!("Error: {:?}", e);
-            error_response(StatusCode::NOT_FOUND, "SNARK not found")
+            None
         }
+    };
+    match count {
+        Some(count) => (StatusCode::OK, Json(serde_json::json!({ "count": count }))).into_response(),
+        None => error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to count SNARKs"),
     }
 }
 
-/// List all SNARKs
-async fn list_snarks(State(nockapp): State) -> Response {
+/// Delete a SNARK
+///
+/// With authentication on, only the submitter or an admin may delete it.
+/// The deletion is recorded in the SNARK's history with the actor.
+async fn delete_snark(
+    State(state): State<SharedState>,
+    principal: Option<Extension<auth::Principal>>,
+    AxumPath(id): AxumPath<u64>,
+    Query(params): Query<DeleteParams>,
+) -> Response {
+    let actor = match auth::act_as(principal.as_deref(), params.actor.as_deref().unwrap_or(""), "actor") {
+        Ok(actor) if actor.is_empty() => ANONYMOUS_ACTOR.to_string(),
+        Ok(actor) => actor,
+        Err(forbidden) => return forbidden.into_response(),
+    };
+
     let mut poke_slab = NounSlab::new();
-    let cause = D(b"list-snarks" as &[u8]);
+
+    // [%delete-snark id=@ud actor=@t by=(unit @t)]
+    let actor = string_to_cord(&mut poke_slab, &actor);
+    let by = auth::owner_required(principal.as_deref()).map(|name| string_to_cord(&mut poke_slab, name));
+    let by = unit(&mut poke_slab, by);
+    let cause = T(&mut poke_slab, &[
+        D(b"delete-snark" as &[u8]),
+        D(id),
+        actor,
+        by,
+    ]);
     poke_slab.set_root(cause);
 
-    let mut app = nockapp.write().await;
-    match app.poke(poke_slab).await {
-        Ok(effects) => {
-            for effect in effects {
-                if let Some(response) = parse_http_response(effect) {
-                    return response;
-                }
+    match poke_kernel(&state, poke_slab, "Failed to delete SNARK").await {
+        Ok(effects) => handle_effects(effects)
+            .unwrap_or_else(|| success_response(StatusCode::OK, "SNARK deleted")),
+        Err(response) => response,
+    }
+}
+
+/// Change the status of a SNARK
+///
+/// The kernel enforces the status state machine and answers 409 for an
+/// illegal transition or a deleted SNARK, and 403 when authentication is on
+/// and the caller is neither the submitter nor an admin. Final verdicts are
+/// the verifier's: with authentication on, only an admin may set them over
+/// the API or act as the verifier. With it off, anyone may.
+async fn update_snark_status(
+    State(state): State<SharedState>,
+    principal: Option<Extension<auth::Principal>>,
+    AxumPath(id): AxumPath<u64>,
+    Json(update): Json<StatusUpdate>,
+) -> Response {
+    if !STATUSES.contains(&update.status.as_str()) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Unknown status {:?}", update.status),
+        );
+    }
+    let actor = match auth::act_as(principal.as_deref(), &update.actor, "actor") {
+        Ok(actor) => actor,
+        Err(forbidden) => return forbidden.into_response(),
+    };
+    if actor.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Actor is required");
+    }
+    let unrestricted = state.admin_key_hash.is_none()
+        || principal.as_deref().is_some_and(auth::Principal::is_admin);
+    if FINAL_STATUSES.contains(&update.status.as_str()) && !unrestricted {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "Only the verifier or an admin may mark a SNARK verified or failed",
+        );
+    }
+    if actor == VERIFIER_ACTOR && !unrestricted {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            &format!("Actor {:?} is reserved for the built-in verifier", VERIFIER_ACTOR),
+        );
+    }
+
+    // Back to pending is a retry, so hold a place in the verification queue
+    let job = if update.status == "pending" {
+        match state.jobs.try_reserve() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                return error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Verification queue is full, try again later",
+                )
             }
-            // Fallback to empty list
-            (StatusCode::OK, Json(SnarkList { snarks: vec![], total: 0 })).into_response()
         }
-        Err(e) => {
-            log::error!("Error: {:?}", e);
-            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list SNARKs")
+    } else {
+        None
+    };
+
+    let reason = update.reason.as_deref();
+    let by = auth::owner_required(principal.as_deref());
+    let response = match set_status(&state, id, &update.status, &actor, reason, by).await {
+        Some(response) => response,
+        None => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update status"),
+    };
+
+    if let Some(permit) = job {
+        if response.status().is_success() {
+            permit.send(id);
         }
     }
+    response
 }
 
-/// Delete a SNARK
-async fn delete_snark(
-    State(nockapp): State,
-    AxumPath(id): AxumPath,
-) -> Response {
+/// Validate list parameters and build the kernel's `query` noun
+///
+/// [status=(unit) system=(unit) submitter=(unit) after=(unit @ud)
+///  before=(unit @ud) order-by=?(%id %submitted) desc=?
+///  cursor=(unit [submitted=@da id=@ud]) limit=@ud]
+fn list_query(slab: &mut NounSlab, params: &ListParams) -> Result<Noun, String> {
+    let status = match params.status.as_deref() {
+        None => None,
+        Some(status) if STATUSES.contains(&status) => Some(status),
+        Some(other) => return Err(format!("Unknown status {:?}", other)),
+    };
+    let order_by = match params.sort.as_deref().unwrap_or("id") {
+        sort @ ("id" | "submitted") => sort,
+        other => return Err(format!("Cannot sort by {:?}; use id or submitted", other)),
+    };
+    let desc = match params.order.as_deref().unwrap_or("asc") {
+        "asc" => false,
+        "desc" => true,
+        other => return Err(format!("Unknown order {:?}; use asc or desc", other)),
+    };
+    let limit = page_size(params.limit)?;
+    let after = params.from.as_deref().map(parse_timestamp).transpose()?;
+    let before = params.to.as_deref().map(parse_timestamp).transpose()?;
+    let cursor = params.cursor.as_deref().map(parse_cursor).transpose()?;
+
+    let status = status.map(|s| string_to_cord(slab, s));
+    let system = params.proof_system.as_deref().map(|s| string_to_cord(slab, s));
+    let submitter = params.submitter.as_deref().map(|s| string_to_cord(slab, s));
+    let circuit = params.circuit.as_deref().map(|s| string_to_cord(slab, s));
+    let cursor = cursor.map(|(submitted, id)| {
+        let submitted = atom_from_bytes(slab, &submitted.to_le_bytes());
+        T(slab, &[submitted, D(id)])
+    });
+    let fields = [
+        unit(slab, status),
+        unit(slab, system),
+        unit(slab, submitter),
+        unit(slab, circuit),
+        unit(slab, after.map(D)),
+        unit(slab, before.map(D)),
+        string_to_cord(slab, order_by),
+        loobean(desc),
+        unit(slab, cursor),
+        D(limit),
+    ];
+    Ok(T(slab, &fields))
+}
+
+/// Page size for a requested `limit`, clamped to `MAX_PAGE_SIZE`
+fn page_size(limit: Option<u64>) -> Result<u64, String> {
+    match limit.unwrap_or(DEFAULT_PAGE_SIZE) {
+        0 => Err("limit must be at least 1".to_string()),
+        limit => Ok(limit.min(MAX_PAGE_SIZE)),
+    }
+}
+
+/// Parse a `next_cursor`, `<submitted>-<id>` with the time as a raw `@da`
+fn parse_cursor(s: &str) -> Result<(u128, u64), String> {
+    s.split_once('-')
+        .and_then(|(submitted, id)| Some((submitted.parse().ok()?, id.parse().ok()?)))
+        .ok_or_else(|| format!("Invalid cursor {:?}; pass next_cursor from the previous page", s))
+}
+
+/// Parse an RFC 3339 timestamp or a bare date into Unix seconds
+fn parse_timestamp(s: &str) -> Result<u64, String> {
+    let seconds = chrono::DateTime::parse_from_rfc3339(s)
+        .map(|t| t.timestamp())
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(|d| d.and_time(chrono::NaiveTime::MIN).and_utc().timestamp())
+        })
+        .map_err(|_| format!("Invalid timestamp {:?}; use RFC 3339 or YYYY-MM-DD", s))?;
+    u64::try_from(seconds).map_err(|_| format!("Timestamp {:?} is before 1970", s))
+}
+
+// ============================================================================
+// Verification
+// ============================================================================
+
+/// Poke `%update-status` and return the kernel's HTTP response
+///
+/// Returns `None` if the poke fails or the kernel gives no response. With
+/// `by`, the kernel refuses the change unless that principal submitted the
+/// SNARK.
+async fn set_status(
+    state: &AppState,
+    id: u64,
+    status: &str,
+    actor: &str,
+    reason: Option<&str>,
+    by: Option<&str>,
+) -> Option<Response> {
     let mut poke_slab = NounSlab::new();
+
+    // [%update-status id=@ud status=@tas actor=@t reason=(unit @t) by=(unit @t)]
+    let status = string_to_cord(&mut poke_slab, status);
+    let actor = string_to_cord(&mut poke_slab, actor);
+    let reason = reason.map(|r| string_to_cord(&mut poke_slab, r));
+    let reason = unit(&mut poke_slab, reason);
+    let by = by.map(|name| string_to_cord(&mut poke_slab, name));
+    let by = unit(&mut poke_slab, by);
     let cause = T(&mut poke_slab, &[
-        D(b"delete-snark" as &[u8]),
+        D(b"update-status" as &[u8]),
         D(id),
+        status,
+        actor,
+        reason,
+        by,
     ]);
     poke_slab.set_root(cause);
 
-    let mut app = nockapp.write().await;
-    match app.poke(poke_slab).await {
-        Ok(effects) => {
-            for effect in effects {
-                if let Some(response) = parse_http_response(effect) {
-                    return response;
-                }
+    let message = format!("Failed to update status of SNARK #{}", id);
+    poke_kernel(state, poke_slab, &message).await.ok().and_then(handle_effects)
+}
+
+// ============================================================================
+// Persistence
+// ============================================================================
+
+/// Peek the full kernel state and write it to the data directory
+///
+/// Called with the write lock held, so the snapshot matches the state the
+/// preceding poke produced.
+async fn save_snapshot(app: &mut NockApp, store: &Store) {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[
+        D(b"x" as &[u8]),
+        D(b"state" as &[u8]),
+        D(0),
+    ]);
+    peek_slab.set_root(path);
+
+    let state = match app.peek(peek_slab).await {
+        Ok(result) => match decode_peek(result).flatten() {
+            Some(state) => state,
+            None => {
+                log::error!("Kernel returned no state to snapshot");
+                return;
             }
-            success_response(StatusCode::OK, "SNARK deleted")
-        }
+        },
         Err(e) => {
-            log::error!("Error: {:?}", e);
-            error_response(StatusCode::NOT_FOUND, "SNARK not found")
+            log::error!("Error peeking kernel state: {:?}", e);
+            return;
         }
+    };
+
+    let mut snapshot = NounSlab::new();
+    snapshot.copy_into(state);
+    if let Err(e) = store.save(snapshot.jam()).await {
+        log::error!("Error writing snapshot to {:?}: {}", store.dir(), e);
     }
 }
 
+/// Restore the newest usable snapshot into a freshly booted kernel
+///
+/// Starts empty when there are no snapshots, but refuses to boot if
+/// snapshots exist and none can be restored, so history is never silently
+/// discarded.
+async fn restore_snapshot(app: &mut NockApp, store: &Store) -> Result<(), Box<dyn Error>> {
+    let snapshots = store.snapshots()?;
+    if snapshots.is_empty() {
+        log::info!("No snapshot in {:?}, starting with empty state", store.dir());
+        return Ok(());
+    }
+
+    for (path, jam) in snapshots {
+        let mut poke_slab = NounSlab::new();
+        let saved = match poke_slab.cue_into(jam) {
+            Ok(saved) => saved,
+            Err(e) => {
+                log::warn!("Skipping unreadable snapshot {:?}: {:?}", path, e);
+                continue;
+            }
+        };
+        let cause = T(&mut poke_slab, &[D(b"restore" as &[u8]), saved]);
+        poke_slab.set_root(cause);
+
+        match app.poke(poke_slab).await {
+            Ok(effects) => {
+                handle_effects(effects);
+                log::info!("Restored kernel state from {:?}", path);
+                return Ok(());
+            }
+            Err(e) => log::warn!("Kernel rejected snapshot {:?}: {:?}", path, e),
+        }
+    }
+
+    log::error!("No snapshot in {:?} could be restored", store.dir());
+    log::error!("See 'Recovering state' in README.md");
+    Err("Unable to restore kernel state".into())
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
 
 /// Convert Rust string to Nock cord (atom)
+///
+/// A cord holds its first byte in the least significant position, so the
+/// UTF-8 bytes are copied as-is into a little-endian atom of any length.
 fn string_to_cord(slab: &mut NounSlab, s: &str) -> Noun {
-    let bytes = s.as_bytes();
+    atom_from_bytes(slab, s.as_bytes())
+}
+
+/// Whether a string is a valid `@tas`: lowercase letters, digits and
+/// hyphens, starting with a letter
+fn is_term(s: &str) -> bool {
+    s.bytes().next().is_some_and(|b| b.is_ascii_lowercase())
+        && s.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Atom of little-endian bytes
+fn atom_from_bytes(slab: &mut NounSlab, bytes: &[u8]) -> Noun {
     if bytes.is_empty() {
         return D(0);
     }
-    // Convert bytes to a big-endian atom
-    let mut result = 0u128;
-    for &byte in bytes.iter().take(16) {
-        result = (result << 8) | byte as u128;
+    // SAFETY: the pointer and length come from the same live `&[u8]`, so
+    // the `bytes.len()` bytes copied into the slab are all in bounds, and
+    // the slice is non-empty, so the atom gets at least one byte
+    let atom = unsafe {
+        IndirectAtom::new_raw_bytes(slab, bytes.len(), bytes.as_ptr()).normalize_as_atom()
+    };
+    atom.as_noun()
+}
+
+/// Decode submitted data: JSON documents as JSON, anything else as Base64
+///
+/// Base64 that decodes to a JSON document, such as an encoded snarkjs
+/// file, is treated as JSON too.
+fn decode_blob(encoded: &Encoded) -> Option<verify::Blob> {
+    match encoded {
+        Encoded::Json(value) => Some(verify::Blob::Json(value.clone())),
+        Encoded::Text(text) if text.trim_start().starts_with('{') => {
+            serde_json::from_str(text).ok().map(verify::Blob::Json)
+        }
+        Encoded::Text(text) => {
+            let bytes = BASE64.decode(text.trim()).ok()?;
+            if bytes.first() == Some(&b'{') {
+                if let Ok(value) = serde_json::from_slice(&bytes) {
+                    return Some(verify::Blob::Json(value));
+                }
+            }
+            Some(verify::Blob::Bytes(bytes))
+        }
     }
-    D(result as u64) // Simplified - full implementation would handle larger strings
+}
+
+/// Convert Nock cord (atom) to Rust string
+fn cord_to_string(noun: Noun) -> Option<String> {
+    let atom = noun.as_atom().ok()?;
+    let bytes = atom.as_ne_bytes();
+    // Atoms are padded to whole words; the cord ends at the last non-zero byte
+    let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    String::from_utf8(bytes[..len].to_vec()).ok()
 }
 
 /// Convert Vec to Nock list
@@ -275,11 +975,152 @@ fn string_list_to_noun(slab: &mut NounSlab, strings: &[String]) -> Noun {
     list
 }
 
-/// Parse HTTP response effect from noun
-fn parse_http_response(effect: Noun) -> Option {
-    // TODO: Implement proper noun parsing
-    // For now, return None and use fallback responses
-    None
+/// Items of a Hoon list
+fn list_items(mut list: Noun) -> Vec<Noun> {
+    let mut items = Vec::new();
+    while let Ok(cell) = list.as_cell() {
+        items.push(cell.head());
+        list = cell.tail();
+    }
+    items
+}
+
+/// Peek the kernel under the shared read lock
+///
+/// Reads never change kernel state, so they run concurrently with each other
+/// and only wait for in-flight pokes.
+async fn peek(state: &AppState, path: NounSlab) -> Result<Option<Option<Noun>>, Box<dyn Error>> {
+    let app = state.nockapp.read().await;
+    let result = app.peek(path).await?;
+    Ok(decode_peek(result))
+}
+
+/// Peek a JSON cord and answer it with `200 OK`
+///
+/// `not_found` answers a path that names nothing, as `[~ ~]`.
+async fn peek_json(state: &AppState, path: NounSlab, not_found: &str) -> Response {
+    match peek(state, path).await {
+        Ok(Some(Some(body))) => json_response(StatusCode::OK, body).unwrap_or_else(|| {
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Invalid response from kernel")
+        }),
+        Ok(Some(None)) => error_response(StatusCode::NOT_FOUND, not_found),
+        Ok(None) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "Invalid response from kernel"),
+        Err(e) => {
+            log::error!("Error peeking kernel: {:?}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read from kernel")
+        }
+    }
+}
+
+/// Peek a path that names one record, `None` if there is none
+async fn peek_found(state: &AppState, path: NounSlab) -> Result<Option<Noun>, String> {
+    match peek(state, path).await {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => Err("Invalid response from kernel".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Poke the kernel and snapshot the new state under the write lock, then
+/// publish the effects' events and queue their webhooks
+///
+/// Returns the effects, or on failure an error response with `message`.
+async fn poke_kernel(state: &AppState, slab: NounSlab, message: &str) -> Result<Vec<Noun>, Response> {
+    let mut app = state.nockapp.write().await;
+    let effects: Vec<Noun> = match app.poke(slab).await {
+        Ok(effects) => effects.into_iter().collect(),
+        Err(e) => {
+            log::error!("{}: {:?}", message, e);
+            return Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, message));
+        }
+    };
+    save_snapshot(&mut app, &state.store).await;
+    drop(app);
+
+    events::publish(&state.events, effects.iter());
+    webhook::queue(&state.completions, effects.iter());
+    Ok(effects)
+}
+
+/// Build a Hoon `(unit)`: `~` or `[~ value]`
+fn unit(slab: &mut NounSlab, value: Option<Noun>) -> Noun {
+    match value {
+        Some(value) => T(slab, &[D(0), value]),
+        None => D(0),
+    }
+}
+
+/// Hoon loobean: `%.y` is 0, `%.n` is 1
+fn loobean(value: bool) -> Noun {
+    D(if value { 0 } else { 1 })
+}
+
+/// Decode a `(unit (unit *))` peek result
+///
+/// `None` means the kernel does not know the path; `Some(None)` that the
+/// path is valid but holds nothing.
+fn decode_peek(result: Noun) -> Option<Option<Noun>> {
+    let outer = result.as_cell().ok()?;
+    match outer.tail().as_cell() {
+        Ok(inner) => Some(Some(inner.tail())),
+        Err(_) => Some(None),
+    }
+}
+
+/// Parse the ID from a `[%snark-submitted id=@ud]` effect
+fn parse_submitted_id(effect: Noun) -> Option<u64> {
+    let cell = effect.as_cell().ok()?;
+    if !cell.head().eq_bytes(b"snark-submitted") {
+        return None;
+    }
+    cell.tail().as_atom().ok()?.as_u64().ok()
+}
+
+/// Parse `[%http-response code=@ud body=@t]` effect into a JSON response
+fn parse_http_response(effect: Noun) -> Option<Response> {
+    let cell = effect.as_cell().ok()?;
+    if !cell.head().eq_bytes(b"http-response") {
+        return None;
+    }
+    let fields = cell.tail().as_cell().ok()?;
+    let code = fields.head().as_atom().ok()?.as_u64().ok()?;
+    let status = StatusCode::from_u16(u16::try_from(code).ok()?).ok()?;
+    json_response(status, fields.tail())
+}
+
+/// Response whose body is a JSON cord rendered by the kernel
+fn json_response(status: StatusCode, body: Noun) -> Option<Response> {
+    let body = cord_to_string(body)?;
+    Some((status, [(header::CONTENT_TYPE, "application/json")], body).into_response())
+}
+
+/// Route `[%log message=@t]` and `[%error message=@t]` effects to the logger
+fn log_effect(effect: Noun) {
+    let Ok(cell) = effect.as_cell() else {
+        return;
+    };
+    let tag = cell.head();
+    if tag.eq_bytes(b"log") {
+        if let Some(message) = cord_to_string(cell.tail()) {
+            log::info!("[kernel] {}", message);
+        }
+    } else if tag.eq_bytes(b"error") {
+        if let Some(message) = cord_to_string(cell.tail()) {
+            log::error!("[kernel] {}", message);
+        }
+    }
+}
+
+/// Log kernel effects and return the first HTTP response among them
+fn handle_effects(effects: impl IntoIterator<Item = Noun>) -> Option<Response> {
+    let mut response = None;
+    for effect in effects {
+        log_effect(effect);
+        if response.is_none() {
+            response = parse_http_response(effect);
+        }
+    }
+    response
 }
 
 /// Create success JSON response
@@ -308,21 +1149,17 @@ fn error_response(status: StatusCode, message: &str) -> Response {
 // ============================================================================
 
 #[tokio::main]
-async fn main() -> Result> {
+async fn main() -> Result<(), Box<dyn Error>> {
+    // Load and validate configuration
+    let config = Config::load()?;
+
     // Initialize logging
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    env_logger::Builder::new().parse_filters(&config.log_level).init();
 
     log::info!("Starting Prover NockApp...");
 
     // Load compiled Hoon kernel
-    let kernel_path = Path::new("prover/out.jam");
-    if !kernel_path.exists() {
-        log::error!("Kernel not found at {:?}", kernel_path);
-        log::error!("Run 'nockup project build' first");
-        return Err("Kernel file not found".into());
-    }
-
-    let kernel_bytes = fs::read(kernel_path)?;
+    let kernel_bytes = fs::read(&config.kernel)?;
     log::info!("Loaded kernel ({} bytes)", kernel_bytes.len());
     
     // Boot NockApp kernel
@@ -333,30 +1170,490 @@ async fn main() -> Result> {
     let mut init_slab = NounSlab::new();
     let init_cause = D(b"init" as &[u8]);
     init_slab.set_root(init_cause);
-    nockapp.poke(init_slab).await?;
+    handle_effects(nockapp.poke(init_slab).await?);
     log::info!("Kernel initialized");
 
+    // Check a snapshot upgrades cleanly instead of serving
+    if let Some(path) = &config.check_snapshot {
+        return check::run(&mut nockapp, path).await;
+    }
+
+    // Restore state saved by a previous run
+    let store = Store::open(&config.data_dir)?;
+    restore_snapshot(&mut nockapp, &store).await?;
+
     // Wrap in Arc for shared access
-    let shared_state = Arc::new(RwLock::new(nockapp));
+    let (jobs, queue) = mpsc::channel(config.queue_size);
+    let (events, _) = broadcast::channel(events::EVENT_BUFFER);
+    let (completions, finished) = mpsc::unbounded_channel();
+    let shared_state = Arc::new(AppState {
+        nockapp: RwLock::new(nockapp),
+        store,
+        jobs,
+        events,
+        completions,
+        webhook: webhook::Settings {
+            url: config.webhook_url.clone(),
+            secret: config.webhook_secret.clone(),
+            attempts: config.webhook_attempts,
+            allow_hosts: config.webhook_allow_hosts.clone(),
+            backoff: webhook::FIRST_BACKOFF,
+        },
+        on_duplicate: config.on_duplicate,
+        idempotency_window: config.idempotency_window,
+        admin_key_hash: config.admin_key.as_deref().map(|key| hex::encode(Sha256::digest(key))),
+    });
+    if shared_state.admin_key_hash.is_some() {
+        log::info!("API-key authentication is on");
+    }
+
+    // Announce finished verifications to webhooks, first finishing
+    // deliveries a restart cut short
+    webhook::spawn(shared_state.clone(), finished).await;
+
+    // Verify submissions in the background, starting with any left over
+    worker::spawn(shared_state.clone(), queue, config.workers, config.verify_timeout);
+    worker::recover(shared_state.clone());
 
     // Build HTTP router
-    let app = Router::new()
+    let app = router(shared_state, &config.web_root);
+
+    // Start HTTP server
+    log::info!("🚀 Prover HTTP server listening on http://{}", config.bind);
+    log::info!("📝 Open your browser to: http://localhost:{}", config.bind.port());
+    
+    let listener = tokio::net::TcpListener::bind(config.bind).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// HTTP router: the API, behind authentication, and the web UI from `web_root`
+fn router(state: SharedState, web_root: &Path) -> Router {
+    Router::new()
         // API routes
         .route("/api/v1/snark", post(submit_snark))
         .route("/api/v1/snark/:id", get(get_snark))
         .route("/api/v1/snark/:id", delete(delete_snark))
+        .route("/api/v1/snark/:id/status", patch(update_snark_status))
+        .route("/api/v1/snark/:id/history", get(get_snark_history))
+        .route("/api/v1/snark/:id/deliveries", get(get_snark_deliveries))
         .route("/api/v1/snarks", get(list_snarks))
+        .route("/api/v1/snarks/count", get(count_snarks))
+        .route("/api/v1/vks", post(vks::register).get(vks::list))
+        .route("/api/v1/vks/:id", get(vks::get))
+        .route("/api/v1/circuits", post(circuits::register).get(circuits::list))
+        .route("/api/v1/circuits/:name", get(circuits::get))
+        .route("/api/v1/events", get(events::stream))
+        .route("/api/v1/ws", get(ws::upgrade))
+        .route("/api/v1/admin/keys", post(auth::create_key).get(auth::list_keys))
+        .route("/api/v1/admin/keys/:id", delete(auth::revoke_key))
+        // Checked on the API routes above, not the static files below
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require))
         // Serve static files (HTML, CSS, JS)
-        .nest_service("/", ServeDir::new("prover/web"))
-        .with_state(shared_state);
+        .nest_service("/", ServeDir::new(web_root))
+        .with_state(state)
+}
 
-    // Start HTTP server
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-    log::info!("🚀 Prover HTTP server listening on http://{}", addr);
-    log::info!("📝 Open your browser to: http://localhost:8080");
-    
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_serialize::Compress;
+    use testing::Segment;
 
-    Ok(())
+    fn round_trip(s: &str) {
+        let mut slab = NounSlab::new();
+        let cord = string_to_cord(&mut slab, s);
+        assert_eq!(cord_to_string(cord).as_deref(), Some(s), "cord of {} bytes", s.len());
+    }
+
+    #[test]
+    fn cords_round_trip() {
+        round_trip("");
+        round_trip("a");
+        round_trip("groth16");
+        // Whole words, and one byte either side
+        round_trip("abcdefg");
+        round_trip("abcdefgh");
+        round_trip("abcdefghi");
+        round_trip("abcdefghijklmnop");
+        // Multi-byte UTF-8 at the end of the atom
+        round_trip("caf\u{e9}");
+        round_trip("proof \u{2713}");
+    }
+
+    #[test]
+    fn base64_proofs_round_trip() {
+        let bytes: Vec<u8> = (0..3072u32).map(|i| (i.wrapping_mul(31) % 251) as u8).collect();
+        let proof = BASE64.encode(bytes);
+        assert!(proof.len() >= 4096);
+        round_trip(&proof);
+        round_trip(&proof[..proof.len() - 1]);
+    }
+
+    #[tokio::test]
+    async fn kernel_escapes_json_text() {
+        let mut app = testing::kernel().await;
+        let submitter = "say \"hi\" \\ back";
+        let notes = "caf\u{e9}\nline two\u{1}\ttabbed \u{1f512}";
+        let id = testing::submit(&mut app, submitter, notes, "escaping").await;
+
+        let detail = testing::peek_json(&mut app, &[Segment::Text("snark"), Segment::Number(id)]).await;
+        let detail: SnarkDetails = serde_json::from_value(detail).expect("detail has every field");
+        assert_eq!(detail.submitter, submitter);
+        assert_eq!(detail.notes, notes);
+    }
+
+    async fn list(state: &SharedState, params: ListParams) -> serde_json::Value {
+        let context = format!("{:?}", params);
+        let (status, page) = testing::json(list_snarks(State(state.clone()), Query(params)).await).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", context, page);
+        page
+    }
+
+    fn ids(page: &serde_json::Value) -> Vec<u64> {
+        page["snarks"]
+            .as_array()
+            .expect("snarks")
+            .iter()
+            .map(|snark| snark["id"].as_u64().unwrap())
+            .collect()
+    }
+
+    /// Submit `count` SNARKs from `submitter`
+    async fn submit_many(state: &SharedState, submitter: &str, count: usize) -> Vec<u64> {
+        let mut app = state.nockapp.write().await;
+        let mut ids = Vec::new();
+        for i in 0..count {
+            ids.push(testing::submit(&mut app, submitter, "", &format!("{}-{}", submitter, i)).await);
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn lists_every_snark_once_across_pages() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let submitted = submit_many(&state, "alice", 5).await;
+
+        for sort in ["id", "submitted"] {
+            for order in ["asc", "desc"] {
+                let mut expected = submitted.clone();
+                if order == "desc" {
+                    expected.reverse();
+                }
+                let mut seen = Vec::new();
+                let mut cursor = None;
+                loop {
+                    let page = list(&state, ListParams {
+                        limit: Some(2),
+                        cursor: cursor.take(),
+                        sort: Some(sort.to_string()),
+                        order: Some(order.to_string()),
+                        ..Default::default()
+                    })
+                    .await;
+                    assert_eq!(page["total"], 5);
+                    assert!(ids(&page).len() <= 2);
+                    seen.extend(ids(&page));
+                    match page["next_cursor"].as_str() {
+                        Some(next) => cursor = Some(next.to_string()),
+                        None => break,
+                    }
+                }
+                assert_eq!(seen, expected, "sort={} order={}", sort, order);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn deletions_while_paging_skip_nothing() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let submitted = submit_many(&state, "alice", 5).await;
+
+        let first = list(&state, ListParams { limit: Some(2), ..Default::default() }).await;
+        assert_eq!(ids(&first), submitted[..2]);
+        let mut app = state.nockapp.write().await;
+        let mut slab = NounSlab::new();
+        let actor = string_to_cord(&mut slab, "alice");
+        let cause = T(&mut slab, &[D(b"delete-snark" as &[u8]), D(submitted[2]), actor, D(0)]);
+        slab.set_root(cause);
+        assert_eq!(testing::poke(&mut app, slab).await.map(|(code, _)| code), Some(200));
+        drop(app);
+
+        let cursor = first["next_cursor"].as_str().map(String::from);
+        let rest = list(&state, ListParams { limit: Some(2), cursor, ..Default::default() }).await;
+        assert_eq!(ids(&rest), submitted[3..]);
+        assert_eq!(rest["total"], 4);
+        assert!(rest["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn filters_lists() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let alice = submit_many(&state, "alice", 2).await;
+        let bob = submit_many(&state, "bob", 1).await;
+        let all: Vec<u64> = alice.iter().chain(&bob).copied().collect();
+        let response = set_status(&state, bob[0], "verifying", VERIFIER_ACTOR, None, None).await;
+        assert_eq!(response.map(|r| r.status()), Some(StatusCode::OK));
+
+        let text = |s: &str| Some(s.to_string());
+        let cases = [
+            (ListParams { submitter: text("alice"), ..Default::default() }, alice.clone()),
+            (ListParams { status: text("verifying"), ..Default::default() }, bob.clone()),
+            (ListParams { status: text("pending"), submitter: text("bob"), ..Default::default() }, vec![]),
+            (ListParams { proof_system: text("groth16"), ..Default::default() }, all.clone()),
+            (ListParams { proof_system: text("plonk"), ..Default::default() }, vec![]),
+            (ListParams { from: text("2000-01-01"), ..Default::default() }, all.clone()),
+            (ListParams { from: text("2100-01-01"), ..Default::default() }, vec![]),
+            (ListParams { to: text("2100-01-01T00:00:00Z"), ..Default::default() }, all.clone()),
+            (ListParams { to: text("2000-01-01"), ..Default::default() }, vec![]),
+        ];
+        for (params, expected) in cases {
+            let context = format!("{:?}", params);
+            let page = list(&state, params).await;
+            assert_eq!(ids(&page), expected, "{}", context);
+            assert_eq!(page["total"], expected.len(), "{}", context);
+        }
+    }
+
+    #[test]
+    fn refuses_bad_list_parameters() {
+        let text = |s: &str| Some(s.to_string());
+        for params in [
+            ListParams { status: text("done"), ..Default::default() },
+            ListParams { sort: text("submitter"), ..Default::default() },
+            ListParams { order: text("up"), ..Default::default() },
+            ListParams { cursor: text("page-two"), ..Default::default() },
+            ListParams { from: text("yesterday"), ..Default::default() },
+            ListParams { to: text("1969-12-31"), ..Default::default() },
+            ListParams { limit: Some(0), ..Default::default() },
+        ] {
+            assert!(list_query(&mut NounSlab::new(), &params).is_err(), "{:?} should be refused", params);
+        }
+    }
+
+    #[test]
+    fn page_sizes_are_clamped() {
+        assert_eq!(page_size(None), Ok(DEFAULT_PAGE_SIZE));
+        assert_eq!(page_size(Some(1)), Ok(1));
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE)), Ok(MAX_PAGE_SIZE));
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE + 1)), Ok(MAX_PAGE_SIZE));
+        assert_eq!(page_size(Some(u64::MAX)), Ok(MAX_PAGE_SIZE));
+        assert!(page_size(Some(0)).is_err());
+    }
+
+    async fn patch_status(state: &SharedState, id: u64, status: &str, actor: &str) -> StatusCode {
+        let update = StatusUpdate { status: status.to_string(), actor: actor.to_string(), reason: None };
+        update_snark_status(State(state.clone()), None, AxumPath(id), Json(update)).await.status()
+    }
+
+    #[tokio::test]
+    async fn status_changes_follow_the_state_machine() {
+        let (state, _jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 16).await;
+        let submitted = submit_many(&state, "alice", 1).await;
+        let id = submitted[0];
+
+        for (to, code) in [
+            ("verified", StatusCode::CONFLICT),
+            ("failed", StatusCode::CONFLICT),
+            ("error", StatusCode::CONFLICT),
+            ("pending", StatusCode::CONFLICT),
+            ("verifying", StatusCode::OK),
+            ("verifying", StatusCode::CONFLICT),
+            ("pending", StatusCode::OK),
+            ("verifying", StatusCode::OK),
+            ("error", StatusCode::OK),
+            ("verifying", StatusCode::CONFLICT),
+            ("verified", StatusCode::CONFLICT),
+            ("pending", StatusCode::OK),
+            ("verifying", StatusCode::OK),
+            ("verified", StatusCode::OK),
+            ("pending", StatusCode::CONFLICT),
+            ("failed", StatusCode::CONFLICT),
+            ("error", StatusCode::CONFLICT),
+        ] {
+            assert_eq!(patch_status(&state, id, to, "alice").await, code, "to {}", to);
+        }
+
+        let failed = submit_many(&state, "bob", 1).await[0];
+        for (to, code) in [("verifying", StatusCode::OK), ("failed", StatusCode::OK), ("pending", StatusCode::CONFLICT)] {
+            assert_eq!(patch_status(&state, failed, to, "bob").await, code, "to {}", to);
+        }
+
+        assert_eq!(patch_status(&state, id, "done", "alice").await, StatusCode::BAD_REQUEST);
+        assert_eq!(patch_status(&state, failed + 1, "verifying", "alice").await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn deleted_snarks_stay_deleted() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let id = submit_many(&state, "alice", 1).await[0];
+        let deleted = delete_snark(State(state.clone()), None, AxumPath(id), Query(DeleteParams::default())).await;
+        assert_eq!(deleted.status(), StatusCode::OK);
+        assert_eq!(patch_status(&state, id, "verifying", "alice").await, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn without_authentication_anyone_may_set_verdicts() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let id = submit_many(&state, "alice", 1).await[0];
+        assert_eq!(patch_status(&state, id, "verifying", VERIFIER_ACTOR).await, StatusCode::OK);
+        assert_eq!(patch_status(&state, id, "verified", "alice").await, StatusCode::OK);
+        assert_eq!(patch_status(&state, id, "verified", "").await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn retries_wait_for_room_in_the_queue() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 1).await;
+        let id = submit_many(&state, "alice", 1).await[0];
+        for to in ["verifying", "error"] {
+            assert_eq!(patch_status(&state, id, to, "alice").await, StatusCode::OK);
+        }
+
+        state.jobs.try_send(u64::MAX).expect("queue has room");
+        assert_eq!(patch_status(&state, id, "pending", "alice").await, StatusCode::SERVICE_UNAVAILABLE);
+        let detail = testing::peek_json(&mut *state.nockapp.write().await, &[Segment::Text("snark"), Segment::Number(id)]).await;
+        assert_eq!(detail["status"], "error", "a refused retry leaves the status alone");
+
+        assert_eq!(jobs.recv().await, Some(u64::MAX));
+        assert_eq!(patch_status(&state, id, "pending", "alice").await, StatusCode::OK);
+        assert_eq!(jobs.recv().await, Some(id), "the retry is queued");
+    }
+
+    /// `(from, to, actor)` of each change in a SNARK's history
+    async fn history(state: &SharedState, id: u64) -> Vec<(serde_json::Value, String, String)> {
+        let (status, body) = testing::json(get_snark_history(State(state.clone()), AxumPath(id)).await).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["history"]
+            .as_array()
+            .expect("history")
+            .iter()
+            .map(|change| {
+                let text = |key: &str| change[key].as_str().expect(key).to_string();
+                (change["from"].clone(), text("to"), text("actor"))
+            })
+            .collect()
+    }
+
+    async fn delete(state: &SharedState, id: u64, actor: Option<&str>) -> StatusCode {
+        let params = DeleteParams { actor: actor.map(String::from) };
+        delete_snark(State(state.clone()), None, AxumPath(id), Query(params)).await.status()
+    }
+
+    #[tokio::test]
+    async fn history_lists_changes_oldest_first() {
+        let (state, _jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 4).await;
+        let id = submit_many(&state, "alice", 1).await[0];
+        assert_eq!(patch_status(&state, id, "verifying", VERIFIER_ACTOR).await, StatusCode::OK);
+        let errored = set_status(&state, id, "error", VERIFIER_ACTOR, Some("Verification timed out"), None).await;
+        assert_eq!(errored.map(|r| r.status()), Some(StatusCode::OK));
+        assert_eq!(patch_status(&state, id, "pending", "alice").await, StatusCode::OK);
+
+        let null = serde_json::Value::Null;
+        let status = |s: &str| serde_json::Value::from(s);
+        let change = |from: serde_json::Value, to: &str, actor: &str| (from, to.to_string(), actor.to_string());
+        let mut expected = vec![
+            change(null, "pending", "alice"),
+            change(status("pending"), "verifying", VERIFIER_ACTOR),
+            change(status("verifying"), "error", VERIFIER_ACTOR),
+            change(status("error"), "pending", "alice"),
+        ];
+        assert_eq!(history(&state, id).await, expected);
+        let (_, body) = testing::json(get_snark_history(State(state.clone()), AxumPath(id)).await).await;
+        assert_eq!(body["history"][2]["reason"], "Verification timed out");
+        assert!(body["history"][1]["reason"].is_null());
+
+        // Deletion ends the history, which outlives the SNARK
+        assert_eq!(delete(&state, id, Some("alice")).await, StatusCode::OK);
+        expected.push(change(status("pending"), "deleted", "alice"));
+        assert_eq!(history(&state, id).await, expected);
+        let detail = get_snark(State(state.clone()), AxumPath(id)).await;
+        assert_eq!(detail.status(), StatusCode::NOT_FOUND);
+
+        let unknown = get_snark_history(State(state.clone()), AxumPath(id + 1)).await;
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn deletions_are_recorded_once() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let id = submit_many(&state, "alice", 1).await[0];
+        assert_eq!(delete(&state, id, None).await, StatusCode::OK);
+        assert_eq!(delete(&state, id, None).await, StatusCode::NOT_FOUND);
+
+        let changes = history(&state, id).await;
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[1], (serde_json::Value::from("pending"), "deleted".to_string(), ANONYMOUS_ACTOR.to_string()));
+    }
+
+    /// Submit a Groth16 proof of the `testing::groth16` circuit as alice
+    async fn submit_groth16(
+        state: &SharedState,
+        proof: serde_json::Value,
+        vk: serde_json::Value,
+        input: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let submission = serde_json::from_value(serde_json::json!({
+            "proof": proof,
+            "verification_key": vk,
+            "public_inputs": [input],
+            "proof_system": "groth16",
+            "submitter": "alice",
+        }))
+        .expect("submission parses");
+        testing::json(submit_snark(State(state.clone()), None, HeaderMap::new(), Json(submission)).await).await
+    }
+
+    fn base64(bytes: &[u8]) -> serde_json::Value {
+        BASE64.encode(bytes).into()
+    }
+
+    #[tokio::test]
+    async fn duplicates_follow_the_policy() {
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        for policy in [DuplicatePolicy::Reject, DuplicatePolicy::Return] {
+            let (mut state, _jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 4).await;
+            Arc::get_mut(&mut state).expect("state is not shared yet").on_duplicate = policy;
+
+            let (status, first) = submit_groth16(&state, base64(&fixture.proof), base64(&fixture.vk), "15").await;
+            assert_eq!(status, StatusCode::CREATED, "{}", first);
+            let (status, again) = submit_groth16(&state, base64(&fixture.proof), base64(&fixture.vk), "15").await;
+            let expected = match policy {
+                DuplicatePolicy::Reject => StatusCode::CONFLICT,
+                DuplicatePolicy::Return => StatusCode::OK,
+            };
+            assert_eq!(status, expected, "{:?}: {}", policy, again);
+            assert_eq!(again["id"], first["id"], "{:?}", policy);
+            let (_, count) = testing::json(count_snarks(State(state.clone())).await).await;
+            assert_eq!(count["count"], 1, "{:?}", policy);
+        }
+    }
+
+    #[tokio::test]
+    async fn equivalent_submissions_are_duplicates() {
+        let (state, _jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 8).await;
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let uncompressed = testing::groth16::<ark_bn254::Bn254>(Compress::No);
+        let (proof, vk) = testing::snarkjs::<ark_bn254::Bn254, _, _>(&fixture, "bn128");
+
+        let (status, first) = submit_groth16(&state, base64(&fixture.proof), base64(&fixture.vk), "15").await;
+        assert_eq!(status, StatusCode::CREATED, "{}", first);
+        let same = [
+            (proof.clone(), vk.clone(), "15"),
+            (base64(&uncompressed.proof), base64(&uncompressed.vk), "15"),
+            (base64(&fixture.proof), base64(&fixture.vk), "0xf"),
+            (proof, vk, "0X0F"),
+        ];
+        for (proof, vk, input) in same {
+            let context = format!("{} {}", proof, input);
+            let (status, body) = submit_groth16(&state, proof, vk, input).await;
+            assert_eq!(status, StatusCode::CONFLICT, "{}: {}", context, body);
+            assert_eq!(body["id"], first["id"], "{}", context);
+        }
+
+        // Another input is another submission, even if it will not verify
+        let (status, body) = submit_groth16(&state, base64(&fixture.proof), base64(&fixture.vk), "16").await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        assert_ne!(body["id"], first["id"]);
+    }
 }
@@ -0,0 +1,65 @@
+//! Snapshot persistence of kernel state
+//!
+//! After every state-changing poke the driver peeks the whole kernel state,
+//! jams it and writes it to `<data-dir>/state.jam`, keeping the previous
+//! snapshot as `state.jam.bak`. On boot the newest readable snapshot is
+//! poked back into the kernel with `%restore`.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use tokio::io::AsyncWriteExt;
+
+const SNAPSHOT: &str = "state.jam";
+const BACKUP: &str = "state.jam.bak";
+const PARTIAL: &str = "state.jam.tmp";
+
+/// Snapshot files in the data directory
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    /// Open the data directory, creating it if needed
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Store { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Existing snapshots, newest first
+    pub fn snapshots(&self) -> io::Result<Vec<(PathBuf, Bytes)>> {
+        let mut found = Vec::new();
+        for name in [SNAPSHOT, BACKUP] {
+            let path = self.dir.join(name);
+            match std::fs::read(&path) {
+                Ok(bytes) => found.push((path, Bytes::from(bytes))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Replace the snapshot, keeping the previous one as a backup
+    ///
+    /// The new snapshot is fully written and synced before it is renamed
+    /// into place, so a crash leaves at least one complete snapshot behind.
+    pub async fn save(&self, jam: Bytes) -> io::Result<()> {
+        let partial = self.dir.join(PARTIAL);
+        let mut file = tokio::fs::File::create(&partial).await?;
+        file.write_all(&jam).await?;
+        file.sync_all().await?;
+
+        let current = self.dir.join(SNAPSHOT);
+        if tokio::fs::try_exists(&current).await? {
+            tokio::fs::rename(&current, self.dir.join(BACKUP)).await?;
+        }
+        tokio::fs::rename(&partial, &current).await
+    }
+}
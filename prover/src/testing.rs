@@ -0,0 +1,282 @@
+//! Helpers for tests that run the compiled kernel
+//!
+//! The kernel is read from `prover/out.jam`; run `nockup project build`
+//! before `cargo test`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{Field, One};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_relations::lc;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+use ark_snark::SNARK;
+use axum::http::StatusCode;
+use axum::response::Response;
+use nockapp::kernel::boot;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{Noun, D, T};
+use nockapp::NockApp;
+use num_bigint::BigUint;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::sync::{broadcast, mpsc, RwLock};
+
+use crate::config::DuplicatePolicy;
+use crate::persist::Store;
+use crate::{
+    cord_to_string, decode_peek, events, handle_effects, parse_submitted_id, string_list_to_noun,
+    string_to_cord, unit, webhook, AppState, SharedState,
+};
+
+/// Compiled kernel
+const KERNEL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/prover/out.jam");
+
+/// Boot the compiled kernel and poke `%init`
+pub async fn kernel() -> NockApp {
+    let bytes = std::fs::read(KERNEL)
+        .unwrap_or_else(|e| panic!("Kernel not found at {}: {}; run 'nockup project build' first", KERNEL, e));
+    let mut app = boot(&bytes).expect("kernel boots");
+    let mut slab = NounSlab::new();
+    slab.set_root(D(b"init" as &[u8]));
+    app.poke(slab).await.expect("kernel initializes");
+    app
+}
+
+/// Shared state around a fresh kernel, with authentication off
+///
+/// Snapshots go to the returned directory, which is removed when dropped.
+/// No workers or webhook dispatcher run; jobs and completions are dropped.
+pub async fn state(webhook: webhook::Settings) -> (SharedState, TempDir) {
+    let (state, _, dir) = state_with_jobs(webhook, 1).await;
+    (state, dir)
+}
+
+/// Shared state like `state`'s, with a verification queue of `capacity`
+/// whose receiving end is returned
+pub async fn state_with_jobs(
+    webhook: webhook::Settings,
+    capacity: usize,
+) -> (SharedState, mpsc::Receiver<u64>, TempDir) {
+    let dir = TempDir::new().expect("temporary directory");
+    let (jobs, receiver) = mpsc::channel(capacity);
+    let (events, _) = broadcast::channel(events::EVENT_BUFFER);
+    let (completions, _) = mpsc::unbounded_channel();
+    let state = Arc::new(AppState {
+        nockapp: RwLock::new(kernel().await),
+        store: Store::open(dir.path()).expect("data directory opens"),
+        jobs,
+        events,
+        completions,
+        webhook,
+        on_duplicate: DuplicatePolicy::Reject,
+        idempotency_window: Duration::from_secs(60),
+        admin_key_hash: None,
+    });
+    (state, receiver, dir)
+}
+
+/// Webhook settings with nothing to deliver
+pub fn no_webhooks() -> webhook::Settings {
+    webhook::Settings {
+        url: None,
+        secret: None,
+        attempts: 1,
+        allow_hosts: Vec::new(),
+        backoff: webhook::FIRST_BACKOFF,
+    }
+}
+
+/// Store a SNARK through `%submit-snark` and return its ID
+///
+/// `digest` must differ between calls, or the second is a duplicate.
+pub async fn submit(app: &mut NockApp, submitter: &str, notes: &str, digest: &str) -> u64 {
+    submit_with_callback(app, submitter, notes, digest, None).await
+}
+
+/// Store a SNARK with a callback URL and return its ID
+pub async fn submit_with_callback(
+    app: &mut NockApp,
+    submitter: &str,
+    notes: &str,
+    digest: &str,
+    callback: Option<&str>,
+) -> u64 {
+    let mut slab = NounSlab::new();
+    let proof = string_to_cord(&mut slab, "cHJvb2Y=");
+    let inputs = string_list_to_noun(&mut slab, &["1".to_string()]);
+    let vk = string_to_cord(&mut slab, "a2V5");
+    let system = string_to_cord(&mut slab, "groth16");
+    let submitter = string_to_cord(&mut slab, submitter);
+    let notes = string_to_cord(&mut slab, notes);
+    let vk_id = string_to_cord(&mut slab, "");
+    let digest = string_to_cord(&mut slab, digest);
+    let callback = callback.map(|url| string_to_cord(&mut slab, url));
+    let callback = unit(&mut slab, callback);
+    let cause = T(&mut slab, &[
+        D(b"submit-snark" as &[u8]),
+        proof,
+        inputs,
+        vk,
+        system,
+        submitter,
+        notes,
+        callback,
+        vk_id,
+        D(0),
+        digest,
+        D(b"reject" as &[u8]),
+        D(0),
+    ]);
+    slab.set_root(cause);
+
+    let effects = app.poke(slab).await.expect("kernel accepts the submission");
+    effects
+        .iter()
+        .find_map(|&effect| parse_submitted_id(effect))
+        .expect("kernel stores the submission")
+}
+
+/// Peek a path of cords and numbers, returning the value if there is one
+pub async fn peek(app: &mut NockApp, path: &[Segment<'_>]) -> Option<Noun> {
+    let mut slab = NounSlab::new();
+    let mut segments = vec![D(b"x" as &[u8])];
+    for segment in path {
+        segments.push(match segment {
+            Segment::Text(text) => string_to_cord(&mut slab, text),
+            Segment::Number(n) => D(*n),
+        });
+    }
+    segments.push(D(0));
+    let path = T(&mut slab, &segments);
+    slab.set_root(path);
+    decode_peek(app.peek(slab).await.expect("kernel answers the peek")).flatten()
+}
+
+/// Peek a path whose value is a JSON cord
+pub async fn peek_json(app: &mut NockApp, path: &[Segment<'_>]) -> serde_json::Value {
+    let body = peek(app, path).await.expect("peek has a value");
+    let body = cord_to_string(body).expect("peek value is a cord");
+    serde_json::from_str(&body).unwrap_or_else(|e| panic!("invalid JSON {:?}: {}", body, e))
+}
+
+/// Poke a cause and return the kernel's HTTP status and body, if any
+pub async fn poke(app: &mut NockApp, slab: NounSlab) -> Option<(u16, String)> {
+    let effects = app.poke(slab).await.expect("kernel accepts the poke");
+    let response = handle_effects(effects)?;
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.ok()?;
+    Some((status, String::from_utf8(body.to_vec()).ok()?))
+}
+
+/// Status and JSON body of a handler's response; `null` if the body is not JSON
+pub async fn json(response: Response) -> (StatusCode, serde_json::Value) {
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("body is readable");
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+/// Part of a peek path
+pub enum Segment<'a> {
+    Text(&'a str),
+    Number(u64),
+}
+
+/// Knows `a` and `b` with `a * b = c` for a public `c`
+#[derive(Clone)]
+struct Product<F> {
+    a: F,
+    b: F,
+}
+
+impl<F: ark_ff::PrimeField> ConstraintSynthesizer<F> for Product<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let c = cs.new_input_variable(|| Ok(self.a * self.b))?;
+        let a = cs.new_witness_variable(|| Ok(self.a))?;
+        let b = cs.new_witness_variable(|| Ok(self.b))?;
+        cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)
+    }
+}
+
+/// Serialized Groth16 proof with its key
+pub struct Groth16Fixture {
+    pub proof: Vec<u8>,
+    pub vk: Vec<u8>,
+    /// Key from a second setup of the same circuit
+    pub other_vk: Vec<u8>,
+}
+
+/// Prove `3 * 5 = 15` with arkworks; the public input is `15`
+pub fn groth16<E: Pairing>(compress: Compress) -> Groth16Fixture {
+    let mut rng = StdRng::seed_from_u64(7);
+    let circuit = Product { a: E::ScalarField::from(3u64), b: E::ScalarField::from(5u64) };
+    let (pk, vk) = Groth16::<E>::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+    let (_, other_vk) = Groth16::<E>::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+    let proof = Groth16::<E>::prove(&pk, circuit, &mut rng).unwrap();
+    Groth16Fixture {
+        proof: serialize(&proof, compress),
+        vk: serialize(&vk, compress),
+        other_vk: serialize(&other_vk, compress),
+    }
+}
+
+fn snarkjs_coordinate<F: Field>(value: F) -> Value {
+    let mut parts: Vec<Value> = value
+        .to_base_prime_field_elements()
+        .map(|part| {
+            let part: BigUint = part.into();
+            Value::String(part.to_string())
+        })
+        .collect();
+    if parts.len() == 1 {
+        parts.remove(0)
+    } else {
+        Value::Array(parts)
+    }
+}
+
+fn snarkjs_point<P: SWCurveConfig>(point: &Affine<P>) -> Value {
+    let (x, y) = point.xy().expect("fixture points are finite");
+    json!([snarkjs_coordinate(*x), snarkjs_coordinate(*y), snarkjs_coordinate(P::BaseField::one())])
+}
+
+/// A compressed fixture as snarkjs 0.7 `proof.json` and `verification_key.json`
+pub fn snarkjs<E, G1, G2>(fixture: &Groth16Fixture, curve: &str) -> (Value, Value)
+where
+    E: Pairing<G1Affine = Affine<G1>, G2Affine = Affine<G2>>,
+    G1: SWCurveConfig,
+    G2: SWCurveConfig,
+{
+    let proof = Proof::<E>::deserialize_compressed(&fixture.proof[..]).unwrap();
+    let vk = VerifyingKey::<E>::deserialize_compressed(&fixture.vk[..]).unwrap();
+    let proof = json!({
+        "pi_a": snarkjs_point(&proof.a),
+        "pi_b": snarkjs_point(&proof.b),
+        "pi_c": snarkjs_point(&proof.c),
+        "protocol": "groth16",
+        "curve": curve,
+    });
+    let vk = json!({
+        "protocol": "groth16",
+        "curve": curve,
+        "nPublic": vk.gamma_abc_g1.len() - 1,
+        "vk_alpha_1": snarkjs_point(&vk.alpha_g1),
+        "vk_beta_2": snarkjs_point(&vk.beta_g2),
+        "vk_gamma_2": snarkjs_point(&vk.gamma_g2),
+        "vk_delta_2": snarkjs_point(&vk.delta_g2),
+        "IC": vk.gamma_abc_g1.iter().map(snarkjs_point).collect::<Vec<_>>(),
+    });
+    (proof, vk)
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T, compress: Compress) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.serialize_with_mode(&mut out, compress).unwrap();
+    out
+}
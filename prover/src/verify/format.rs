@@ -0,0 +1,514 @@
+//! Proof and verification key formats
+//!
+//! Submissions may use the native output of common toolchains. Each is
+//! converted to the one encoding its verifier reads, which is also what the
+//! kernel stores:
+//!
+//! | Proof system | Accepted | Stored as |
+//! |--------------|----------|-----------|
+//! | Groth16 | arkworks, gnark (BN254), snarkjs JSON | arkworks, compressed |
+//! | PLONK | gnark | gnark |
+//! | STARK | plonky2 | plonky2 |
+//!
+//! Proofs and keys are converted separately, so a key registered once can
+//! serve proofs in any accepted format.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use ark_ec::pairing::Pairing;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{Field, One, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use serde_json::Value;
+
+use super::gnark::{checked, Decoder};
+use super::groth16;
+
+/// Encoding of a submitted proof and verification key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Detect from the data
+    Auto,
+    /// arkworks `CanonicalSerialize`
+    Arkworks,
+    /// gnark `WriteTo`
+    Gnark,
+    /// snarkjs `proof.json` and `verification_key.json`
+    Snarkjs,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Format::Auto),
+            "arkworks" => Ok(Format::Arkworks),
+            "gnark" => Ok(Format::Gnark),
+            "snarkjs" => Ok(Format::Snarkjs),
+            other => Err(format!(
+                "Unknown format {:?}; use auto, arkworks, gnark or snarkjs",
+                other
+            )),
+        }
+    }
+}
+
+/// A proof or verification key as submitted
+#[derive(Debug)]
+pub enum Blob {
+    Bytes(Vec<u8>),
+    Json(Value),
+}
+
+/// Convert a proof to the stored encoding
+pub fn normalize_proof(proof_system: &str, format: Format, proof: Blob) -> Result<Vec<u8>> {
+    match (proof_system, format, proof) {
+        ("groth16", Format::Auto | Format::Snarkjs, Blob::Json(proof)) => snarkjs_groth16_proof(&proof),
+        ("groth16", Format::Auto, Blob::Bytes(proof)) => {
+            arkworks_or_gnark(&proof, groth16::canonical_proof, gnark_groth16_proof)
+        }
+        ("groth16", Format::Arkworks, Blob::Bytes(proof)) => groth16::canonical_proof(&proof),
+        ("groth16", Format::Gnark, Blob::Bytes(proof)) => gnark_groth16_proof(&proof),
+        (system, format, proof) => as_submitted(system, format, proof),
+    }
+}
+
+/// Convert a verification key to the stored encoding
+pub fn normalize_vk(proof_system: &str, format: Format, vk: Blob) -> Result<Vec<u8>> {
+    match (proof_system, format, vk) {
+        ("groth16", Format::Auto | Format::Snarkjs, Blob::Json(vk)) => snarkjs_groth16_vk(&vk),
+        ("groth16", Format::Auto, Blob::Bytes(vk)) => {
+            arkworks_or_gnark(&vk, groth16::canonical_vk, gnark_groth16_vk)
+        }
+        ("groth16", Format::Arkworks, Blob::Bytes(vk)) => groth16::canonical_vk(&vk),
+        ("groth16", Format::Gnark, Blob::Bytes(vk)) => gnark_groth16_vk(&vk),
+        (system, format, vk) => as_submitted(system, format, vk),
+    }
+}
+
+/// Read Groth16 bytes of unknown origin, trying arkworks first
+fn arkworks_or_gnark(
+    bytes: &[u8],
+    arkworks: fn(&[u8]) -> Result<Vec<u8>>,
+    gnark: fn(&[u8]) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    arkworks(bytes).or_else(|arkworks| {
+        gnark(bytes).map_err(|gnark| {
+            anyhow!("Neither arkworks ({}) nor gnark ({}) Groth16 data", arkworks, gnark)
+        })
+    })
+}
+
+/// Data for systems whose verifier reads the toolchain's own encoding
+fn as_submitted(proof_system: &str, format: Format, blob: Blob) -> Result<Vec<u8>> {
+    match (proof_system, format, blob) {
+        ("plonk", Format::Auto | Format::Gnark, Blob::Bytes(bytes)) => Ok(bytes),
+        ("plonk", Format::Snarkjs, _) | ("plonk", _, Blob::Json(_)) => {
+            bail!("snarkjs PLONK proofs use a different transcript from gnark and cannot be verified")
+        }
+        ("plonk", Format::Arkworks, _) => bail!("PLONK proofs must be in gnark format"),
+
+        ("stark", Format::Auto, Blob::Bytes(bytes)) => Ok(bytes),
+        ("stark", ..) => bail!("STARK proofs must be plonky2 bytes"),
+
+        // No verifier to convert for; keep bytes as submitted
+        (_, Format::Auto, Blob::Bytes(bytes)) => Ok(bytes),
+
+        (system, format, _) => bail!("Cannot read {:?} data as a {} proof", format, system),
+    }
+}
+
+// ============================================================================
+// snarkjs
+// ============================================================================
+
+/// Check a snarkjs document is for Groth16 and read its curve
+fn snarkjs_curve<'a>(doc: &'a Value, name: &str) -> Result<Option<&'a str>> {
+    let protocol = doc.get("protocol").and_then(Value::as_str).unwrap_or("groth16");
+    if protocol != "groth16" {
+        bail!("snarkjs {} is for {}, not groth16", name, protocol);
+    }
+    Ok(doc.get("curve").and_then(Value::as_str))
+}
+
+/// Convert a snarkjs Groth16 proof to compressed arkworks bytes
+fn snarkjs_groth16_proof(proof: &Value) -> Result<Vec<u8>> {
+    match snarkjs_curve(proof, "proof")? {
+        Some("bn128" | "bn254") => snarkjs_proof_on::<ark_bn254::Bn254, _, _>(proof),
+        Some("bls12381" | "bls12_381") => snarkjs_proof_on::<ark_bls12_381::Bls12_381, _, _>(proof),
+        Some(other) => bail!("Unsupported snarkjs curve {:?}", other),
+        // Older snarkjs leaves the curve out; the points lie on only one
+        None => snarkjs_proof_on::<ark_bn254::Bn254, _, _>(proof)
+            .or_else(|_| snarkjs_proof_on::<ark_bls12_381::Bls12_381, _, _>(proof)),
+    }
+}
+
+/// Convert a snarkjs Groth16 verification key to compressed arkworks bytes
+fn snarkjs_groth16_vk(vk: &Value) -> Result<Vec<u8>> {
+    match snarkjs_curve(vk, "verification key")? {
+        Some("bn128" | "bn254") => snarkjs_vk_on::<ark_bn254::Bn254, _, _>(vk),
+        Some("bls12381" | "bls12_381") => snarkjs_vk_on::<ark_bls12_381::Bls12_381, _, _>(vk),
+        Some(other) => bail!("Unsupported snarkjs curve {:?}", other),
+        None => bail!("snarkjs verification key has no curve"),
+    }
+}
+
+fn snarkjs_proof_on<E, G1, G2>(proof: &Value) -> Result<Vec<u8>>
+where
+    E: Pairing<G1Affine = Affine<G1>, G2Affine = Affine<G2>>,
+    G1: SWCurveConfig,
+    G2: SWCurveConfig,
+{
+    let proof = Proof::<E> {
+        a: snarkjs_point(field(proof, "pi_a")?)?,
+        b: snarkjs_point(field(proof, "pi_b")?)?,
+        c: snarkjs_point(field(proof, "pi_c")?)?,
+    };
+    compressed(&proof)
+}
+
+fn snarkjs_vk_on<E, G1, G2>(vk: &Value) -> Result<Vec<u8>>
+where
+    E: Pairing<G1Affine = Affine<G1>, G2Affine = Affine<G2>>,
+    G1: SWCurveConfig,
+    G2: SWCurveConfig,
+{
+    let gamma_abc_g1 = field(vk, "IC")?
+        .as_array()
+        .ok_or_else(|| anyhow!("snarkjs IC is not a list"))?
+        .iter()
+        .map(snarkjs_point)
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(n_public) = vk.get("nPublic").and_then(Value::as_u64) {
+        if gamma_abc_g1.len() as u64 != n_public + 1 {
+            bail!("snarkjs IC has {} points for {} public inputs", gamma_abc_g1.len(), n_public);
+        }
+    }
+    let vk = VerifyingKey::<E> {
+        alpha_g1: snarkjs_point(field(vk, "vk_alpha_1")?)?,
+        beta_g2: snarkjs_point(field(vk, "vk_beta_2")?)?,
+        gamma_g2: snarkjs_point(field(vk, "vk_gamma_2")?)?,
+        delta_g2: snarkjs_point(field(vk, "vk_delta_2")?)?,
+        gamma_abc_g1,
+    };
+    compressed(&vk)
+}
+
+fn field<'a>(doc: &'a Value, name: &str) -> Result<&'a Value> {
+    doc.get(name).ok_or_else(|| anyhow!("snarkjs data has no {:?}", name))
+}
+
+/// Parse a snarkjs point: `[x, y, z]` of decimal strings, with each
+/// coordinate a `[c0, c1]` pair for G2. snarkjs writes affine points, so `z`
+/// is 1, or 0 for the point at infinity.
+fn snarkjs_point<P: SWCurveConfig>(value: &Value) -> Result<Affine<P>> {
+    let coords = value
+        .as_array()
+        .filter(|coords| coords.len() == 3)
+        .ok_or_else(|| anyhow!("snarkjs point is not [x, y, z]: {}", value))?;
+    let x: P::BaseField = snarkjs_coordinate(&coords[0])?;
+    let y: P::BaseField = snarkjs_coordinate(&coords[1])?;
+    let z: P::BaseField = snarkjs_coordinate(&coords[2])?;
+
+    if z.is_zero() {
+        return Ok(Affine::<P>::zero());
+    }
+    if !z.is_one() {
+        bail!("snarkjs point is not in affine form");
+    }
+    checked(Affine::<P>::new_unchecked(x, y))
+}
+
+fn snarkjs_coordinate<F: Field>(value: &Value) -> Result<F> {
+    let parts: Vec<&Value> = match value {
+        Value::Array(parts) => parts.iter().collect(),
+        part => vec![part],
+    };
+    let elems = parts
+        .into_iter()
+        .map(|part| {
+            part.as_str()
+                .and_then(|s| F::BasePrimeField::from_str(s).ok())
+                .ok_or_else(|| anyhow!("snarkjs coordinate is not a decimal field element: {}", part))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    F::from_base_prime_field_elems(&elems).ok_or_else(|| {
+        anyhow!(
+            "snarkjs coordinate has {} parts, expected {}",
+            elems.len(),
+            F::extension_degree()
+        )
+    })
+}
+
+// ============================================================================
+// gnark
+// ============================================================================
+
+/// Convert a gnark BN254 Groth16 `WriteTo` proof to compressed arkworks bytes
+///
+/// Circuits using gnark's Pedersen commitment extension cannot be expressed
+/// as arkworks keys and are rejected.
+fn gnark_groth16_proof(proof: &[u8]) -> Result<Vec<u8>> {
+    let mut dec = Decoder::new(proof);
+    let proof = Proof::<ark_bn254::Bn254> {
+        a: dec.g1()?,
+        b: dec.g2()?,
+        c: dec.g1()?,
+    };
+    if !dec.is_empty() {
+        let commitments = dec.g1_vec()?;
+        let _pok = dec.g1()?;
+        if !commitments.is_empty() {
+            bail!("gnark Groth16 proofs with commitments are not supported");
+        }
+    }
+    if !dec.is_empty() {
+        bail!("Trailing bytes after gnark Groth16 proof");
+    }
+    compressed(&proof)
+}
+
+/// Convert a gnark BN254 Groth16 `WriteTo` verification key to compressed
+/// arkworks bytes
+fn gnark_groth16_vk(vk: &[u8]) -> Result<Vec<u8>> {
+    let mut dec = Decoder::new(vk);
+    let alpha_g1 = dec.g1()?;
+    let _beta_g1 = dec.g1()?;
+    let beta_g2 = dec.g2()?;
+    let gamma_g2 = dec.g2()?;
+    let _delta_g1 = dec.g1()?;
+    let delta_g2 = dec.g2()?;
+    let gamma_abc_g1 = dec.g1_vec()?;
+    if !dec.is_empty() {
+        let committed = dec.u32()?;
+        for _ in 0..committed {
+            dec.u64_vec()?;
+        }
+        if committed > 0 || dec.u32()? > 0 {
+            bail!("gnark Groth16 keys with commitments are not supported");
+        }
+    }
+    if !dec.is_empty() {
+        bail!("Trailing bytes after gnark Groth16 verification key");
+    }
+
+    let vk = VerifyingKey::<ark_bn254::Bn254> {
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        gamma_abc_g1,
+    };
+    compressed(&vk)
+}
+
+fn compressed<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    value
+        .serialize_compressed(&mut bytes)
+        .map_err(|e| anyhow!("Serialization failed: {}", e))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    //! gnark bytes are written here from arkworks fixtures, following the
+    //! layout of gnark v0.10.
+
+    use super::*;
+    use crate::testing::{self, Groth16Fixture as Fixture};
+    use crate::verify::gnark::g1_bytes;
+    use crate::verify::Verdict;
+    use ark_bn254::{Bn254, Fq, G1Affine, G2Affine};
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::{BigInteger, PrimeField};
+    use ark_serialize::{CanonicalDeserialize, Compress};
+    use serde_json::json;
+
+    const SMALLEST: u8 = 0b10 << 6;
+    const LARGEST: u8 = 0b11 << 6;
+
+    fn be(value: &Fq) -> Vec<u8> {
+        value.into_bigint().to_bytes_be()
+    }
+
+    fn gnark_g1(point: &G1Affine, compress: bool) -> Vec<u8> {
+        if !compress {
+            return g1_bytes(point).to_vec();
+        }
+        let (x, y) = point.xy().unwrap();
+        let mut out = be(x);
+        out[0] |= if *y > -*y { LARGEST } else { SMALLEST };
+        out
+    }
+
+    /// gnark orders Fp2 coordinates as A1 || A0
+    fn gnark_g2(point: &G2Affine, compress: bool) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        let mut out = [be(&x.c1), be(&x.c0)].concat();
+        if compress {
+            out[0] |= if *y > -*y { LARGEST } else { SMALLEST };
+        } else {
+            out.extend([be(&y.c1), be(&y.c0)].concat());
+        }
+        out
+    }
+
+    /// The fixture as gnark `WriteTo` bytes, without commitments
+    fn gnark(fixture: &Fixture, compress: bool) -> (Vec<u8>, Vec<u8>) {
+        let proof = Proof::<Bn254>::deserialize_compressed(&fixture.proof[..]).unwrap();
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(&fixture.vk[..]).unwrap();
+        let proof = [
+            gnark_g1(&proof.a, compress),
+            gnark_g2(&proof.b, compress),
+            gnark_g1(&proof.c, compress),
+        ]
+        .concat();
+
+        // arkworks keys have no beta or delta in G1; any point will do
+        let unused = gnark_g1(&G1Affine::generator(), compress);
+        let mut key = [
+            gnark_g1(&vk.alpha_g1, compress),
+            unused.clone(),
+            gnark_g2(&vk.beta_g2, compress),
+            gnark_g2(&vk.gamma_g2, compress),
+            unused,
+            gnark_g2(&vk.delta_g2, compress),
+        ]
+        .concat();
+        key.extend((vk.gamma_abc_g1.len() as u32).to_be_bytes());
+        for point in &vk.gamma_abc_g1 {
+            key.extend(gnark_g1(point, compress));
+        }
+        // No committed inputs and no commitment keys
+        key.extend(0u32.to_be_bytes());
+        key.extend(0u32.to_be_bytes());
+        (proof, key)
+    }
+
+    fn inputs() -> Vec<String> {
+        vec!["15".to_string()]
+    }
+
+    #[test]
+    fn converts_snarkjs_to_arkworks() {
+        let bn254 = testing::groth16::<Bn254>(Compress::Yes);
+        let bls12_381 = testing::groth16::<Bls12_381>(Compress::Yes);
+        let cases = [
+            (&bn254, testing::snarkjs::<Bn254, _, _>(&bn254, "bn128")),
+            (&bls12_381, testing::snarkjs::<Bls12_381, _, _>(&bls12_381, "bls12381")),
+        ];
+        for (fixture, (proof, vk)) in cases {
+            for format in [Format::Auto, Format::Snarkjs] {
+                let converted = normalize_proof("groth16", format, Blob::Json(proof.clone())).unwrap();
+                assert_eq!(converted, fixture.proof);
+                let converted = normalize_vk("groth16", format, Blob::Json(vk.clone())).unwrap();
+                assert_eq!(converted, fixture.vk);
+            }
+
+            // Older snarkjs proofs name no curve
+            let mut bare = proof.clone();
+            bare.as_object_mut().unwrap().remove("curve");
+            assert_eq!(normalize_proof("groth16", Format::Snarkjs, Blob::Json(bare)).unwrap(), fixture.proof);
+
+            assert_eq!(groth16::verify(&fixture.proof, &fixture.vk, &inputs()), Verdict::Verified);
+        }
+    }
+
+    #[test]
+    fn snarkjs_g2_coordinates_are_c0_first() {
+        let fixture = testing::groth16::<Bn254>(Compress::Yes);
+        let (mut proof, _) = testing::snarkjs::<Bn254, _, _>(&fixture, "bn128");
+        for coordinate in proof["pi_b"].as_array_mut().unwrap().iter_mut().take(2) {
+            coordinate.as_array_mut().unwrap().reverse();
+        }
+        let swapped = normalize_proof("groth16", Format::Snarkjs, Blob::Json(proof));
+        assert!(!matches!(swapped, Ok(bytes) if bytes == fixture.proof));
+    }
+
+    #[test]
+    fn converts_gnark_to_arkworks() {
+        let fixture = testing::groth16::<Bn254>(Compress::Yes);
+        for compress in [true, false] {
+            let (proof, vk) = gnark(&fixture, compress);
+            for format in [Format::Auto, Format::Gnark] {
+                let converted = normalize_proof("groth16", format, Blob::Bytes(proof.clone())).unwrap();
+                assert_eq!(converted, fixture.proof, "compressed: {}", compress);
+                let converted = normalize_vk("groth16", format, Blob::Bytes(vk.clone())).unwrap();
+                assert_eq!(converted, fixture.vk, "compressed: {}", compress);
+            }
+        }
+
+        // A proof may end with an empty commitment list and its proof of knowledge
+        let (mut proof, _) = gnark(&fixture, true);
+        proof.extend(0u32.to_be_bytes());
+        proof.extend(gnark_g1(&G1Affine::generator(), true));
+        assert_eq!(normalize_proof("groth16", Format::Gnark, Blob::Bytes(proof)).unwrap(), fixture.proof);
+    }
+
+    #[test]
+    fn gnark_g2_points_are_a1_first_and_flag_their_root() {
+        let fixture = testing::groth16::<Bn254>(Compress::Yes);
+        let proof = Proof::<Bn254>::deserialize_compressed(&fixture.proof[..]).unwrap();
+        let a = gnark_g1(&proof.a, true);
+        let c = gnark_g1(&proof.c, true);
+        let read = |b: Vec<u8>| normalize_proof("groth16", Format::Gnark, Blob::Bytes([&a[..], &b, &c].concat()));
+        assert_eq!(read(gnark_g2(&proof.b, true)).unwrap(), fixture.proof);
+        assert_eq!(read(gnark_g2(&proof.b, false)).unwrap(), fixture.proof);
+
+        // The other root is the negated point
+        let mut other_root = gnark_g2(&proof.b, true);
+        other_root[0] ^= LARGEST ^ SMALLEST;
+        let negated = read(other_root).unwrap();
+        assert_ne!(negated, fixture.proof);
+        assert!(matches!(groth16::verify(&negated, &fixture.vk, &inputs()), Verdict::Failed(_)));
+
+        // A0 || A1 is a different x, if a point at all
+        let b = gnark_g2(&proof.b, true);
+        let mut swapped = [&b[32..], &b[..32]].concat();
+        swapped[0] |= b[0] & (LARGEST | SMALLEST);
+        swapped[32] &= !(LARGEST | SMALLEST);
+        assert!(!matches!(read(swapped), Ok(bytes) if bytes == fixture.proof));
+    }
+
+    #[test]
+    fn rejects_gnark_commitments() {
+        let fixture = testing::groth16::<Bn254>(Compress::Yes);
+        let (mut proof, mut vk) = gnark(&fixture, true);
+        let point = gnark_g1(&G1Affine::generator(), true);
+
+        proof.extend(1u32.to_be_bytes());
+        proof.extend(&point);
+        proof.extend(&point);
+        let error = normalize_proof("groth16", Format::Gnark, Blob::Bytes(proof)).unwrap_err();
+        assert!(error.to_string().contains("commitments"), "{}", error);
+
+        // One committed input list, holding input 1
+        vk.truncate(vk.len() - 8);
+        vk.extend(1u32.to_be_bytes());
+        vk.extend(1u32.to_be_bytes());
+        vk.extend(1u64.to_be_bytes());
+        vk.extend(0u32.to_be_bytes());
+        let error = normalize_vk("groth16", Format::Gnark, Blob::Bytes(vk)).unwrap_err();
+        assert!(error.to_string().contains("commitments"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_snarkjs_plonk() {
+        let proof = json!({ "protocol": "plonk", "curve": "bn128" });
+        for format in [Format::Auto, Format::Snarkjs] {
+            let error = normalize_proof("plonk", format, Blob::Json(proof.clone())).unwrap_err();
+            assert!(error.to_string().contains("snarkjs PLONK"), "{}", error);
+        }
+        assert!(normalize_proof("plonk", Format::Snarkjs, Blob::Bytes(vec![1, 2, 3])).is_err());
+
+        // Nor is a snarkjs PLONK document read as Groth16
+        let error = normalize_proof("groth16", Format::Snarkjs, Blob::Json(proof)).unwrap_err();
+        assert!(error.to_string().contains("not groth16"), "{}", error);
+    }
+}
@@ -0,0 +1,169 @@
+//! gnark-crypto binary encoding of BN254 values
+//!
+//! gnark writes integers big-endian, field elements as 32-byte big-endian
+//! canonical integers and curve points with the compression flags in the two
+//! most significant bits of the first byte. Slices carry a `u32` length
+//! prefix.
+
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::{Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+
+const MASK: u8 = 0b11 << 6;
+const UNCOMPRESSED: u8 = 0b00 << 6;
+const COMPRESSED_INFINITY: u8 = 0b01 << 6;
+const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+
+/// Reader over a gnark-encoded byte stream
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Decoder { bytes, pos: 0 }
+    }
+
+    /// True once every byte has been consumed
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn peek(&self) -> Result<u8> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| anyhow!("Unexpected end of data at byte {}", self.pos))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos + n;
+        if end > self.bytes.len() {
+            bail!("Unexpected end of data at byte {}", self.pos);
+        }
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    pub fn u64_vec(&mut self) -> Result<Vec<u64>> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.u64()).collect()
+    }
+
+    pub fn fr(&mut self) -> Result<Fr> {
+        canonical(self.take(32)?)
+    }
+
+    pub fn fr_vec(&mut self) -> Result<Vec<Fr>> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.fr()).collect()
+    }
+
+    pub fn g1(&mut self) -> Result<G1Affine> {
+        let flag = self.peek()? & MASK;
+        if flag == UNCOMPRESSED {
+            let raw = self.take(64)?;
+            if raw.iter().all(|&b| b == 0) {
+                return Ok(G1Affine::zero());
+            }
+            let point = G1Affine::new_unchecked(canonical(&raw[..32])?, canonical(&raw[32..])?);
+            return checked(point);
+        }
+
+        let raw = self.take(32)?;
+        if flag == COMPRESSED_INFINITY {
+            return Ok(G1Affine::zero());
+        }
+        let x: Fq = canonical(&unflagged(raw))?;
+        let point = G1Affine::get_point_from_x_unchecked(x, flag == COMPRESSED_LARGEST)
+            .ok_or_else(|| anyhow!("G1 point is not on the curve"))?;
+        checked(point)
+    }
+
+    pub fn g1_vec(&mut self) -> Result<Vec<G1Affine>> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.g1()).collect()
+    }
+
+    pub fn g2(&mut self) -> Result<G2Affine> {
+        let flag = self.peek()? & MASK;
+        if flag == UNCOMPRESSED {
+            let raw = self.take(128)?;
+            if raw.iter().all(|&b| b == 0) {
+                return Ok(G2Affine::zero());
+            }
+            let x = Fq2::new(canonical(&raw[32..64])?, canonical(&raw[..32])?);
+            let y = Fq2::new(canonical(&raw[96..])?, canonical(&raw[64..96])?);
+            return checked(G2Affine::new_unchecked(x, y));
+        }
+
+        let raw = self.take(64)?;
+        if flag == COMPRESSED_INFINITY {
+            return Ok(G2Affine::zero());
+        }
+        // gnark orders Fp2 coordinates as A1 || A0
+        let x = Fq2::new(canonical(&raw[32..])?, canonical(&unflagged(&raw[..32]))?);
+        let point = G2Affine::get_point_from_x_unchecked(x, flag == COMPRESSED_LARGEST)
+            .ok_or_else(|| anyhow!("G2 point is not on the curve"))?;
+        checked(point)
+    }
+}
+
+/// Uncompressed point encoding, as produced by gnark's `Marshal`
+pub fn g1_bytes(point: &G1Affine) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    if let Some((x, y)) = point.xy() {
+        out[..32].copy_from_slice(&x.into_bigint().to_bytes_be());
+        out[32..].copy_from_slice(&y.into_bigint().to_bytes_be());
+    }
+    out
+}
+
+/// Big-endian field element encoding, as produced by gnark's `Marshal`
+pub fn fr_bytes(value: &Fr) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&value.into_bigint().to_bytes_be());
+    out
+}
+
+fn unflagged(raw: &[u8]) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    buf.copy_from_slice(raw);
+    buf[0] &= !MASK;
+    buf
+}
+
+fn canonical<F: PrimeField>(bytes: &[u8]) -> Result<F> {
+    let value = F::from_be_bytes_mod_order(bytes);
+    if value.into_bigint().to_bytes_be() != bytes {
+        bail!("Field element is not in canonical form");
+    }
+    Ok(value)
+}
+
+/// Reject points off the curve or outside the prime-order subgroup
+pub fn checked<P: SWCurveConfig>(point: Affine<P>) -> Result<Affine<P>> {
+    if !point.is_on_curve() {
+        bail!("Point is not on the curve");
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        bail!("Point is not in the prime-order subgroup");
+    }
+    Ok(point)
+}
@@ -0,0 +1,198 @@
+//! Groth16 verifier for BN254 and BLS12-381
+//!
+//! Proofs and verification keys are arkworks `CanonicalSerialize` bytes,
+//! compressed or uncompressed. The curve is inferred from the proof length.
+
+use anyhow::{anyhow, bail, Result};
+use ark_ec::pairing::Pairing;
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use ark_snark::SNARK;
+
+use super::{check_input_count, parse_field_elements, KeyInfo, Verdict};
+
+/// Curves supported by the Groth16 backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Curve {
+    Bn254,
+    Bls12_381,
+}
+
+/// Infer curve and point encoding from the serialized proof length
+fn detect(proof: &[u8]) -> Result<(Curve, Compress)> {
+    match proof.len() {
+        128 => Ok((Curve::Bn254, Compress::Yes)),
+        256 => Ok((Curve::Bn254, Compress::No)),
+        192 => Ok((Curve::Bls12_381, Compress::Yes)),
+        384 => Ok((Curve::Bls12_381, Compress::No)),
+        n => bail!("Unrecognized Groth16 proof length: {} bytes", n),
+    }
+}
+
+/// Verify a Groth16 proof
+pub fn verify(proof: &[u8], vk: &[u8], inputs: &[String]) -> Verdict {
+    match detect(proof) {
+        Ok((Curve::Bn254, compress)) => verify_on::<ark_bn254::Bn254>(proof, vk, inputs, compress),
+        Ok((Curve::Bls12_381, compress)) => verify_on::<ark_bls12_381::Bls12_381>(proof, vk, inputs, compress),
+        Err(e) => Verdict::Error(e.to_string()),
+    }
+}
+
+/// Check public inputs against the curve's scalar field and the key
+pub fn check_inputs(proof: &[u8], vk: &[u8], inputs: &[String]) -> Result<()> {
+    let Ok((curve, _)) = detect(proof) else {
+        return Ok(());
+    };
+    match curve {
+        Curve::Bn254 => check_inputs_on::<ark_bn254::Bn254>(vk, inputs),
+        Curve::Bls12_381 => check_inputs_on::<ark_bls12_381::Bls12_381>(vk, inputs),
+    }
+}
+
+fn check_inputs_on<E: Pairing>(vk: &[u8], inputs: &[String]) -> Result<()> {
+    parse_field_elements::<E::ScalarField>(inputs)?;
+    let vk = VerifyingKey::<E>::deserialize_compressed(vk)
+        .or_else(|_| VerifyingKey::<E>::deserialize_uncompressed(vk));
+    match vk {
+        Ok(vk) => check_input_count(vk.gamma_abc_g1.len().saturating_sub(1), inputs.len()),
+        Err(_) => bail!("Verification key is not for the proof's curve"),
+    }
+}
+
+/// Curve and input count of a stored (compressed) key
+pub fn key_info(vk: &[u8]) -> Option<KeyInfo> {
+    let inputs = |len: usize| Some(len.saturating_sub(1));
+    if let Ok(vk) = VerifyingKey::<ark_bn254::Bn254>::deserialize_compressed(vk) {
+        return Some(KeyInfo { curve: "bn254", inputs: inputs(vk.gamma_abc_g1.len()) });
+    }
+    let vk = VerifyingKey::<ark_bls12_381::Bls12_381>::deserialize_compressed(vk).ok()?;
+    Some(KeyInfo { curve: "bls12-381", inputs: inputs(vk.gamma_abc_g1.len()) })
+}
+
+/// Re-encode proof bytes in compressed form
+pub fn canonical_proof(proof: &[u8]) -> Result<Vec<u8>> {
+    let (curve, compress) = detect(proof)?;
+    match curve {
+        Curve::Bn254 => reencode::<Proof<ark_bn254::Bn254>>(proof, compress),
+        Curve::Bls12_381 => reencode::<Proof<ark_bls12_381::Bls12_381>>(proof, compress),
+    }
+    .map_err(|e| anyhow!("Malformed proof: {}", e))
+}
+
+/// Re-encode verification key bytes in compressed form
+///
+/// A key has no length that gives its curve away, so each curve and
+/// encoding is tried in turn; a match must use every byte.
+pub fn canonical_vk(vk: &[u8]) -> Result<Vec<u8>> {
+    [Compress::Yes, Compress::No]
+        .into_iter()
+        .find_map(|compress| {
+            reencode::<VerifyingKey<ark_bn254::Bn254>>(vk, compress)
+                .or_else(|_| reencode::<VerifyingKey<ark_bls12_381::Bls12_381>>(vk, compress))
+                .ok()
+        })
+        .ok_or_else(|| anyhow!("Malformed verification key: not a BN254 or BLS12-381 Groth16 key"))
+}
+
+fn reencode<T: CanonicalSerialize + CanonicalDeserialize>(bytes: &[u8], compress: Compress) -> Result<Vec<u8>> {
+    let mut reader = bytes;
+    let value = T::deserialize_with_mode(&mut reader, compress, Validate::Yes)
+        .map_err(|e| anyhow!("{}", e))?;
+    if !reader.is_empty() {
+        bail!("{} trailing bytes", reader.len());
+    }
+    let mut out = Vec::new();
+    value
+        .serialize_compressed(&mut out)
+        .map_err(|e| anyhow!("Serialization failed: {}", e))?;
+    Ok(out)
+}
+
+fn decode<E: Pairing>(proof: &[u8], vk: &[u8], compress: Compress) -> Result<(Proof<E>, VerifyingKey<E>)> {
+    let proof = Proof::<E>::deserialize_with_mode(proof, compress, Validate::Yes)
+        .map_err(|e| anyhow!("Malformed proof: {}", e))?;
+    let vk = VerifyingKey::<E>::deserialize_compressed(vk)
+        .or_else(|_| VerifyingKey::<E>::deserialize_uncompressed(vk))
+        .map_err(|e| anyhow!("Malformed verification key: {}", e))?;
+    Ok((proof, vk))
+}
+
+fn verify_on<E: Pairing>(proof: &[u8], vk: &[u8], inputs: &[String], compress: Compress) -> Verdict {
+    let decoded = decode::<E>(proof, vk, compress)
+        .and_then(|(proof, vk)| Ok((proof, vk, parse_field_elements::<E::ScalarField>(inputs)?)));
+    let (proof, vk, inputs) = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => return Verdict::Error(e.to_string()),
+    };
+    if let Err(e) = check_input_count(vk.gamma_abc_g1.len().saturating_sub(1), inputs.len()) {
+        return Verdict::Failed(e.to_string());
+    }
+
+    match Groth16::<E>::verify(&vk, &inputs, &proof) {
+        Ok(true) => Verdict::Verified,
+        Ok(false) => Verdict::Failed("Pairing check failed".to_string()),
+        Err(e) => Verdict::Error(format!("Verifier error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Groth16Fixture as Fixture};
+
+    fn fixtures() -> Vec<Fixture> {
+        [Compress::Yes, Compress::No]
+            .into_iter()
+            .flat_map(|compress| {
+                [
+                    testing::groth16::<ark_bn254::Bn254>(compress),
+                    testing::groth16::<ark_bls12_381::Bls12_381>(compress),
+                ]
+            })
+            .collect()
+    }
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn verifies_arkworks_proofs() {
+        for f in fixtures() {
+            assert_eq!(verify(&f.proof, &f.vk, &inputs(&["15"])), Verdict::Verified);
+            assert_eq!(verify(&f.proof, &f.vk, &inputs(&["0xf"])), Verdict::Verified);
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_inputs() {
+        for f in fixtures() {
+            assert!(matches!(verify(&f.proof, &f.vk, &inputs(&["16"])), Verdict::Failed(_)));
+        }
+    }
+
+    #[test]
+    fn rejects_proofs_under_another_key() {
+        for f in fixtures() {
+            assert!(matches!(verify(&f.proof, &f.other_vk, &inputs(&["15"])), Verdict::Failed(_)));
+        }
+    }
+
+    #[test]
+    fn rejects_input_count_mismatch() {
+        for f in fixtures() {
+            assert!(matches!(verify(&f.proof, &f.vk, &inputs(&[])), Verdict::Failed(_)));
+            assert!(matches!(verify(&f.proof, &f.vk, &inputs(&["15", "1"])), Verdict::Failed(_)));
+            assert!(check_inputs(&f.proof, &f.vk, &inputs(&["15", "1"])).is_err());
+        }
+    }
+
+    #[test]
+    fn undecodable_submissions_are_errors() {
+        for f in fixtures() {
+            assert!(matches!(verify(&f.proof, &f.vk, &inputs(&["fifteen"])), Verdict::Error(_)));
+            assert!(matches!(verify(&f.proof[1..], &f.vk, &inputs(&["15"])), Verdict::Error(_)));
+            assert!(matches!(verify(&f.proof, &f.vk[1..], &inputs(&["15"])), Verdict::Error(_)));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Off-chain proof verification
+//!
+//! Nockchain cannot verify user-provided proofs yet, so the driver checks
+//! submissions locally and reports the outcome to the kernel through the
+//! `%update-status` cause.
+
+mod format;
+mod gnark;
+mod groth16;
+mod plonk;
+mod stark;
+
+use anyhow::{anyhow, bail, Result};
+use ark_ff::PrimeField;
+use num_bigint::BigUint;
+
+pub use format::{normalize_proof, normalize_vk, Blob, Format};
+
+/// Outcome of verifying a single submission
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Proof is valid for the verification key and public inputs
+    Verified,
+    /// Proof was rejected, with the reason
+    Failed(String),
+    /// Verifier could not run to completion, including when the proof, key
+    /// or public inputs cannot be decoded; unlike `Failed`, not final
+    Error(String),
+}
+
+impl Verdict {
+    /// Status tag stored in the kernel's `snark-entry`
+    pub fn status(&self) -> &'static str {
+        match self {
+            Verdict::Verified => "verified",
+            Verdict::Failed(_) => "failed",
+            Verdict::Error(_) => "error",
+        }
+    }
+
+    /// Message stored in the entry's `error-message`, if any
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Verdict::Verified => None,
+            Verdict::Failed(reason) | Verdict::Error(reason) => Some(reason),
+        }
+    }
+}
+
+/// Whether there is a verifier backend for `proof_system`
+pub fn supports(proof_system: &str) -> bool {
+    matches!(proof_system, "groth16" | "plonk" | "stark")
+}
+
+/// Verify a submission with the backend for its proof system
+///
+/// Returns `None` when there is no backend for `proof_system`; such entries
+/// stay `%pending`.
+pub fn verify(proof_system: &str, proof: &[u8], vk: &[u8], inputs: &[String]) -> Option<Verdict> {
+    match proof_system {
+        "groth16" => Some(groth16::verify(proof, vk, inputs)),
+        "plonk" => Some(plonk::verify(proof, vk, inputs)),
+        "stark" => Some(stark::verify(proof, vk, inputs)),
+        _ => None,
+    }
+}
+
+/// Check public inputs before a submission is stored
+///
+/// Inputs must be elements of the proof system's field, and as many as the
+/// verification key declares. Keys that cannot be read are left for the
+/// verifier to reject.
+pub fn check_inputs(proof_system: &str, proof: &[u8], vk: &[u8], inputs: &[String]) -> Result<()> {
+    match proof_system {
+        "groth16" => groth16::check_inputs(proof, vk, inputs),
+        "plonk" => plonk::check_inputs(vk, inputs),
+        "stark" => stark::check_inputs(vk, inputs),
+        _ => Ok(()),
+    }
+}
+
+/// What a verification key says about the circuit it belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub curve: &'static str,
+    /// Public inputs the key expects, if it records them
+    pub inputs: Option<usize>,
+}
+
+/// Curves, or for STARKs fields, `proof_system` can be verified on
+pub fn curves(proof_system: &str) -> &'static [&'static str] {
+    match proof_system {
+        "groth16" => &["bn254", "bls12-381"],
+        "plonk" => &["bn254"],
+        "stark" => &["goldilocks"],
+        _ => &[],
+    }
+}
+
+/// Read the curve and input count from a stored verification key
+///
+/// Returns `None` for keys that cannot be read or have no backend.
+pub fn key_info(proof_system: &str, vk: &[u8]) -> Option<KeyInfo> {
+    match proof_system {
+        "groth16" => groth16::key_info(vk),
+        "plonk" => Some(plonk::key_info(vk)),
+        "stark" => stark::key_info(vk),
+        _ => None,
+    }
+}
+
+fn check_input_count(expected: usize, got: usize) -> Result<()> {
+    if got != expected {
+        bail!("Verification key expects {} public inputs, got {}", expected, got);
+    }
+    Ok(())
+}
+
+/// Parse public inputs as scalar field elements (decimal or 0x-hex)
+fn parse_field_elements<F: PrimeField>(inputs: &[String]) -> Result<Vec<F>> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            parse_field_element(input).ok_or_else(|| {
+                anyhow!(
+                    "Public input {} is not a decimal or 0x-hex field element: {:?}",
+                    i,
+                    input
+                )
+            })
+        })
+        .collect()
+}
+
+/// Parse an unsigned integer written in decimal or 0x-hex
+///
+/// Digits are checked first: `BigUint::parse_bytes` also takes `_`
+/// separators, which would let one value be written several ways.
+pub fn parse_uint(input: &str) -> Option<BigUint> {
+    let (digits, radix) = match input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        Some(digits) if digits.bytes().all(|b| b.is_ascii_hexdigit()) => (digits, 16),
+        None if input.bytes().all(|b| b.is_ascii_digit()) => (input, 10),
+        _ => return None,
+    };
+    BigUint::parse_bytes(digits.as_bytes(), radix)
+}
+
+/// Parse a field element, rejecting values at or above the modulus rather
+/// than reducing them
+fn parse_field_element<F: PrimeField>(input: &str) -> Option<F> {
+    let value = parse_uint(input)?;
+    let modulus: BigUint = F::MODULUS.into();
+    if value >= modulus {
+        return None;
+    }
+    Some(F::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use ark_serialize::Compress;
+
+    #[test]
+    fn uints_are_plain_decimal_or_hex() {
+        assert_eq!(parse_uint("1234"), Some(BigUint::from(1234u32)));
+        assert_eq!(parse_uint("0xff"), Some(BigUint::from(255u32)));
+        assert_eq!(parse_uint("0XFF"), Some(BigUint::from(255u32)));
+        for bad in ["", "0x", "1_000", "0xf_f", "+1", "-1", "1e3", "0b101", "12 34", " 1", "1 ", "0x ff"] {
+            assert_eq!(parse_uint(bad), None, "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn every_system_reads_inputs_alike() {
+        let groth16 = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let cases: [(&str, &[u8], &[u8]); 3] =
+            [("groth16", &groth16.proof, &groth16.vk), ("plonk", b"", b""), ("stark", b"", b"")];
+        for (system, proof, vk) in cases {
+            for good in ["5", "0x5", "0X05"] {
+                let checked = check_inputs(system, proof, vk, &[good.to_string()]);
+                assert!(checked.is_ok(), "{} rejected {:?}: {:?}", system, good, checked);
+            }
+            for bad in ["+5", " 5", "5 ", "0x", "-5", ""] {
+                let checked = check_inputs(system, proof, vk, &[bad.to_string()]);
+                assert!(checked.is_err(), "{} accepted {:?}", system, bad);
+            }
+        }
+    }
+
+    #[test]
+    fn undecodable_submissions_are_errors() {
+        let inputs = ["1".to_string()];
+        for system in ["groth16", "plonk", "stark"] {
+            let verdict = verify(system, b"not a proof", b"not a key", &inputs).unwrap();
+            assert!(matches!(verdict, Verdict::Error(_)), "{}: {:?}", system, verdict);
+        }
+    }
+}
@@ -0,0 +1,570 @@
+//! PLONK verifier (KZG over BN254)
+//!
+//! Reads gnark's binary `VerifyingKey` and `Proof` layouts (v0.10, including
+//! BSB22 commitments) and replays its SHA-256 Fiat-Shamir transcript.
+//! halo2/PSE layouts are not read: their keys do not carry the circuit's
+//! gates, so they fail to decode like any other foreign key.
+
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use sha2::{Digest, Sha256};
+
+use super::gnark::{fr_bytes, g1_bytes, Decoder};
+use super::{check_input_count, parse_field_elements, KeyInfo, Verdict};
+
+/// Domain separator gnark uses to hash BSB22 commitments into the field
+const BSB22_DST: &[u8] = b"BSB22-Plonk";
+
+/// Verify a PLONK proof
+pub fn verify(proof: &[u8], vk: &[u8], inputs: &[String]) -> Verdict {
+    let decoded = VerifyingKey::decode(vk).and_then(|vk| {
+        let proof = Proof::decode(proof)?;
+        Ok((vk, proof, parse_field_elements::<Fr>(inputs)?))
+    });
+    let (vk, proof, inputs) = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => return Verdict::Error(e.to_string()),
+    };
+
+    match check(&vk, &proof, &inputs) {
+        Ok(true) => Verdict::Verified,
+        Ok(false) => Verdict::Failed("PLONK verification failed".to_string()),
+        Err(e) => Verdict::Failed(e.to_string()),
+    }
+}
+
+/// Check public inputs against BN254's scalar field and the key
+pub fn check_inputs(vk: &[u8], inputs: &[String]) -> Result<()> {
+    parse_field_elements::<Fr>(inputs)?;
+    match VerifyingKey::decode(vk) {
+        Ok(vk) => check_input_count(vk.nb_public as usize, inputs.len()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Curve and input count of a key
+pub fn key_info(vk: &[u8]) -> KeyInfo {
+    let inputs = VerifyingKey::decode(vk).ok().map(|vk| vk.nb_public as usize);
+    KeyInfo { curve: "bn254", inputs }
+}
+
+/// gnark PLONK verifying key
+struct VerifyingKey {
+    size: u64,
+    size_inv: Fr,
+    generator: Fr,
+    nb_public: u64,
+    coset_shift: Fr,
+    s: [G1Affine; 3],
+    ql: G1Affine,
+    qr: G1Affine,
+    qm: G1Affine,
+    qo: G1Affine,
+    qk: G1Affine,
+    qcp: Vec<G1Affine>,
+    kzg_g1: G1Affine,
+    kzg_g2: [G2Affine; 2],
+    commitment_indexes: Vec<u64>,
+}
+
+impl VerifyingKey {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut d = Decoder::new(bytes);
+        let vk = VerifyingKey {
+            size: d.u64()?,
+            size_inv: d.fr()?,
+            generator: d.fr()?,
+            nb_public: d.u64()?,
+            coset_shift: d.fr()?,
+            s: [d.g1()?, d.g1()?, d.g1()?],
+            ql: d.g1()?,
+            qr: d.g1()?,
+            qm: d.g1()?,
+            qo: d.g1()?,
+            qk: d.g1()?,
+            qcp: d.g1_vec()?,
+            kzg_g1: d.g1()?,
+            kzg_g2: [d.g2()?, d.g2()?],
+            commitment_indexes: d.u64_vec()?,
+        };
+        if !d.is_empty() {
+            bail!("Trailing bytes after PLONK verification key");
+        }
+
+        if !vk.size.is_power_of_two() {
+            bail!("PLONK domain size {} is not a power of two", vk.size);
+        }
+        if vk.size_inv * Fr::from(vk.size) != Fr::one() || vk.generator.pow([vk.size]) != Fr::one() {
+            bail!("Malformed PLONK verification key: inconsistent domain");
+        }
+        Ok(vk)
+    }
+}
+
+/// gnark PLONK proof
+struct Proof {
+    lro: [G1Affine; 3],
+    z: G1Affine,
+    h: [G1Affine; 3],
+    batched_h: G1Affine,
+    claimed_values: Vec<Fr>,
+    z_shifted_h: G1Affine,
+    z_shifted_value: Fr,
+    bsb22: Vec<G1Affine>,
+}
+
+impl Proof {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut d = Decoder::new(bytes);
+        let proof = Proof {
+            lro: [d.g1()?, d.g1()?, d.g1()?],
+            z: d.g1()?,
+            h: [d.g1()?, d.g1()?, d.g1()?],
+            batched_h: d.g1()?,
+            claimed_values: d.fr_vec()?,
+            z_shifted_h: d.g1()?,
+            z_shifted_value: d.fr()?,
+            bsb22: d.g1_vec()?,
+        };
+        if !d.is_empty() {
+            bail!("Trailing bytes after PLONK proof");
+        }
+        Ok(proof)
+    }
+}
+
+/// gnark's Fiat-Shamir transcript: each challenge hashes its name, the
+/// previous challenge and its bindings
+#[derive(Default)]
+struct Transcript {
+    previous: Option<[u8; 32]>,
+}
+
+impl Transcript {
+    fn challenge<'a>(&mut self, name: &str, bindings: impl IntoIterator<Item = &'a [u8]>) -> Fr {
+        let mut hasher = Sha256::new();
+        hasher.update(name.as_bytes());
+        if let Some(previous) = &self.previous {
+            hasher.update(previous);
+        }
+        for binding in bindings {
+            hasher.update(binding);
+        }
+        let digest: [u8; 32] = hasher.finalize().into();
+        self.previous = Some(digest);
+        Fr::from_be_bytes_mod_order(&digest)
+    }
+}
+
+fn check(vk: &VerifyingKey, proof: &Proof, public: &[Fr]) -> Result<bool> {
+    if proof.bsb22.len() != vk.qcp.len() || vk.commitment_indexes.len() != vk.qcp.len() {
+        bail!("BSB22 commitment count does not match the verification key");
+    }
+    check_input_count(vk.nb_public as usize, public.len())?;
+    if proof.claimed_values.len() != 6 + vk.qcp.len() {
+        bail!(
+            "Proof has {} claimed values, expected {}",
+            proof.claimed_values.len(),
+            6 + vk.qcp.len()
+        );
+    }
+
+    // Challenges
+    let public_data: Vec<Vec<u8>> = vk
+        .s
+        .iter()
+        .chain([&vk.ql, &vk.qr, &vk.qm, &vk.qo, &vk.qk])
+        .chain(&vk.qcp)
+        .map(|p| g1_bytes(p).to_vec())
+        .chain(public.iter().map(|x| fr_bytes(x).to_vec()))
+        .chain(proof.lro.iter().map(|p| g1_bytes(p).to_vec()))
+        .collect();
+    let mut transcript = Transcript::default();
+    let gamma = transcript.challenge("gamma", public_data.iter().map(Vec::as_slice));
+    let beta = transcript.challenge("beta", []);
+    let alpha_deps: Vec<[u8; 64]> = proof.bsb22.iter().chain([&proof.z]).map(g1_bytes).collect();
+    let alpha = transcript.challenge("alpha", alpha_deps.iter().map(|b| &b[..]));
+    let zeta_deps: Vec<[u8; 64]> = proof.h.iter().map(g1_bytes).collect();
+    let zeta = transcript.challenge("zeta", zeta_deps.iter().map(|b| &b[..]));
+
+    // ζⁿ-1 and L₁(ζ)
+    let one = Fr::one();
+    let zeta_n = zeta.pow([vk.size]);
+    let zh = zeta_n - one;
+    let lagrange_zero = invert(zeta - one)? * zh * vk.size_inv;
+
+    // PI(ζ) = ∑ Lᵢ(ζ)·wᵢ, plus the hashed BSB22 commitments
+    let mut pi = Fr::zero();
+    let mut w = one;
+    for x in public {
+        pi += zh * invert(zeta - w)? * vk.size_inv * w * x;
+        w *= vk.generator;
+    }
+    for (commitment, index) in proof.bsb22.iter().zip(&vk.commitment_indexes) {
+        let hashed = hash_to_field(&g1_bytes(commitment), BSB22_DST);
+        let w_i = vk.generator.pow([vk.nb_public + index]);
+        pi += zh * w_i * invert(zeta - w_i)? * vk.size_inv * hashed;
+    }
+
+    let cv = &proof.claimed_values;
+    let (lin, l, r, o, s1, s2) = (cv[0], cv[1], cv[2], cv[3], cv[4], cv[5]);
+    let zu = proof.z_shifted_value;
+
+    // Opening of the linearised polynomial must equal -constLin
+    let alpha2_l0 = lagrange_zero * alpha * alpha;
+    let perm = (l + beta * s1 + gamma) * (r + beta * s2 + gamma);
+    let const_lin = -(pi - alpha2_l0 + alpha * perm * (o + gamma) * zu);
+    if const_lin != lin {
+        return Ok(false);
+    }
+
+    // Linearised polynomial digest
+    let u = vk.coset_shift;
+    let s1_coeff = perm * beta * alpha * zu;
+    let s2_coeff = -(alpha
+        * (l + beta * zeta + gamma)
+        * (r + beta * u * zeta + gamma)
+        * (o + beta * u * u * zeta + gamma));
+    let zeta_n2 = zeta.pow([vk.size + 2]);
+
+    let mut bases = proof.bsb22.clone();
+    bases.extend([vk.ql, vk.qr, vk.qm, vk.qo, vk.qk, vk.s[2], proof.z]);
+    bases.extend(proof.h);
+    let mut scalars = cv[6..].to_vec();
+    scalars.extend([
+        l,
+        r,
+        l * r,
+        o,
+        one,
+        s1_coeff,
+        alpha2_l0 + s2_coeff,
+        -zh,
+        -(zeta_n2 * zh),
+        -(zeta_n2 * zeta_n2 * zh),
+    ]);
+    let linearised = msm(&bases, &scalars);
+
+    // Fold the openings at ζ
+    let mut digests = vec![linearised, proof.lro[0], proof.lro[1], proof.lro[2], vk.s[0], vk.s[1]];
+    digests.extend(&vk.qcp);
+    let fold_data: Vec<Vec<u8>> = std::iter::once(fr_bytes(&zeta).to_vec())
+        .chain(digests.iter().map(|p| g1_bytes(p).to_vec()))
+        .chain(cv.iter().map(|x| fr_bytes(x).to_vec()))
+        .chain(std::iter::once(fr_bytes(&zu).to_vec()))
+        .collect();
+    let fold_gamma = Transcript::default().challenge("gamma", fold_data.iter().map(Vec::as_slice));
+    let powers: Vec<Fr> = std::iter::successors(Some(one), |p| Some(*p * fold_gamma))
+        .take(digests.len())
+        .collect();
+    let folded_digest = msm(&digests, &powers);
+    let folded_value: Fr = cv.iter().zip(&powers).map(|(v, p)| *v * p).sum();
+
+    Ok(batch_verify(
+        vk,
+        &[
+            (folded_digest, proof.batched_h, folded_value, zeta),
+            (proof.z, proof.z_shifted_h, zu, zeta * vk.generator),
+        ],
+    ))
+}
+
+/// Check KZG openings `(digest, quotient, value, point)` with one pairing
+fn batch_verify(vk: &VerifyingKey, openings: &[(G1Affine, G1Affine, Fr, Fr)]) -> bool {
+    let mut rng = rand::thread_rng();
+    let mut lhs = G1Projective::zero();
+    let mut quotients = G1Projective::zero();
+    let mut value = Fr::zero();
+    for (i, (digest, quotient, claimed, point)) in openings.iter().enumerate() {
+        let lambda = if i == 0 { Fr::one() } else { Fr::rand(&mut rng) };
+        lhs += *digest * lambda + *quotient * (lambda * point);
+        quotients += *quotient * lambda;
+        value += lambda * claimed;
+    }
+    lhs -= vk.kzg_g1 * value;
+
+    Bn254::multi_pairing(
+        [lhs.into_affine(), (-quotients).into_affine()],
+        [vk.kzg_g2[0], vk.kzg_g2[1]],
+    )
+    .is_zero()
+}
+
+fn msm(bases: &[G1Affine], scalars: &[Fr]) -> G1Affine {
+    bases
+        .iter()
+        .zip(scalars)
+        .map(|(base, scalar)| base.into_group() * scalar)
+        .sum::<G1Projective>()
+        .into_affine()
+}
+
+fn invert(value: Fr) -> Result<Fr> {
+    value
+        .inverse()
+        .ok_or_else(|| anyhow!("Evaluation point lies in the PLONK domain"))
+}
+
+/// RFC 9380 hash-to-field with `expand_message_xmd` over SHA-256
+fn hash_to_field(msg: &[u8], dst: &[u8]) -> Fr {
+    const LEN: usize = 48;
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+
+    let b0: [u8; 32] = Sha256::new()
+        .chain_update([0u8; 64])
+        .chain_update(msg)
+        .chain_update((LEN as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize()
+        .into();
+
+    let mut uniform = Vec::with_capacity(64);
+    let mut previous = [0u8; 32];
+    for i in 1..=2u8 {
+        let mut input = b0;
+        if i > 1 {
+            input.iter_mut().zip(&previous).for_each(|(a, b)| *a ^= b);
+        }
+        previous = Sha256::new()
+            .chain_update(input)
+            .chain_update([i])
+            .chain_update(&dst_prime)
+            .finalize()
+            .into();
+        uniform.extend_from_slice(&previous);
+    }
+    Fr::from_be_bytes_mod_order(&uniform[..LEN])
+}
+
+#[cfg(test)]
+mod tests {
+    //! Fixtures are made by a minimal prover that writes gnark v0.10's
+    //! layout: no blinding or BSB22 commitments, and a setup whose secret
+    //! is known, so every commitment is `[p(τ)]₁`.
+
+    use super::*;
+    use ark_bn254::Fq;
+    use ark_ff::{BigInteger, FftField};
+    use ark_poly::univariate::DensePolynomial;
+    use ark_poly::{DenseUVPolynomial, EvaluationDomain, Evaluations, Polynomial, Radix2EvaluationDomain};
+
+    type Poly = DensePolynomial<Fr>;
+
+    /// Rows in the circuit
+    const N: usize = 4;
+
+    struct Fixture {
+        proof: Vec<u8>,
+        vk: Vec<u8>,
+    }
+
+    /// Prove knowledge of `a` and `b` with `a * b = x` for public `x`
+    ///
+    /// Row 0 holds the public input, row 1 the product; the copy
+    /// constraint ties row 0's left wire to row 1's output.
+    fn fixture(a: u64, b: u64, tau: u64) -> Fixture {
+        let domain = Radix2EvaluationDomain::<Fr>::new(N).unwrap();
+        let tau = Fr::from(tau);
+        let u = Fr::GENERATOR;
+        let (a, b) = (Fr::from(a), Fr::from(b));
+        let x = a * b;
+        let zero = Fr::zero();
+        let one = Fr::one();
+        let interpolate = |values: Vec<Fr>| Evaluations::from_vec_and_domain(values, domain).interpolate();
+        let commit = |p: &Poly| (G1Affine::generator() * p.evaluate(&tau)).into_affine();
+
+        let wires = [[x, a, zero, zero], [zero, b, zero, zero], [zero, x, zero, zero]];
+        let ql = interpolate(vec![-one, zero, zero, zero]);
+        let qr = interpolate(vec![zero; N]);
+        let qm = interpolate(vec![zero, one, zero, zero]);
+        let qo = interpolate(vec![zero, -one, zero, zero]);
+        let qk = interpolate(vec![zero; N]);
+        let pi = interpolate(vec![x, zero, zero, zero]);
+
+        // Wire (column, row) labels, and the permutation swapping the
+        // public input with the product
+        let id = |column: usize, row: usize| u.pow([column as u64]) * domain.element(row);
+        let sigma = |column: usize, row: usize| match (column, row) {
+            (0, 0) => id(2, 1),
+            (2, 1) => id(0, 0),
+            _ => id(column, row),
+        };
+        let s: Vec<Poly> = (0..3).map(|c| interpolate((0..N).map(|r| sigma(c, r)).collect())).collect();
+
+        let kzg_g2 = [G2Affine::generator(), (G2Affine::generator() * tau).into_affine()];
+        let mut vk = Vec::new();
+        vk.extend((N as u64).to_be_bytes());
+        vk.extend(fr_bytes(&domain.size_inv()));
+        vk.extend(fr_bytes(&domain.group_gen()));
+        vk.extend(1u64.to_be_bytes());
+        vk.extend(fr_bytes(&u));
+        for p in s.iter().chain([&ql, &qr, &qm, &qo, &qk]) {
+            vk.extend(g1_bytes(&commit(p)));
+        }
+        vk.extend(0u32.to_be_bytes());
+        vk.extend(g1_bytes(&G1Affine::generator()));
+        vk.extend(kzg_g2.iter().flat_map(g2_bytes));
+        vk.extend(0u32.to_be_bytes());
+        let key = VerifyingKey::decode(&vk).unwrap();
+
+        // Round 1: wires
+        let lro: Vec<Poly> = wires.iter().map(|w| interpolate(w.to_vec())).collect();
+        let lro_commits: Vec<G1Affine> = lro.iter().map(commit).collect();
+        let public_data: Vec<Vec<u8>> = key
+            .s
+            .iter()
+            .chain([&key.ql, &key.qr, &key.qm, &key.qo, &key.qk])
+            .map(|p| g1_bytes(p).to_vec())
+            .chain([fr_bytes(&x).to_vec()])
+            .chain(lro_commits.iter().map(|p| g1_bytes(p).to_vec()))
+            .collect();
+        let mut transcript = Transcript::default();
+        let gamma = transcript.challenge("gamma", public_data.iter().map(Vec::as_slice));
+        let beta = transcript.challenge("beta", []);
+
+        // Round 2: permutation accumulator
+        let mut z_values = vec![one];
+        for row in 0..N - 1 {
+            let num: Fr = (0..3).map(|c| wires[c][row] + beta * id(c, row) + gamma).product();
+            let den: Fr = (0..3).map(|c| wires[c][row] + beta * sigma(c, row) + gamma).product();
+            z_values.push(z_values[row] * num / den);
+        }
+        let z = interpolate(z_values);
+        let z_commit = commit(&z);
+        let alpha = transcript.challenge("alpha", [&g1_bytes(&z_commit)[..]]);
+
+        // Round 3: quotient, split into chunks of N + 2 coefficients
+        let constant = |c: Fr| Poly::from_coefficients_vec(vec![c]);
+        let shifted = |p: &Poly, by: Fr| {
+            Poly::from_coefficients_vec(p.coeffs.iter().enumerate().map(|(i, c)| *c * by.pow([i as u64])).collect())
+        };
+        let gate = &(&(&(&(&ql * &lro[0]) + &(&qr * &lro[1])) + &(&(&qm * &lro[0]) * &lro[1])) + &(&qo * &lro[2]))
+            + &(&qk + &pi);
+        let sigma_product = (0..3)
+            .map(|c| &(&lro[c] + &(&s[c] * beta)) + &constant(gamma))
+            .fold(shifted(&z, domain.group_gen()), |acc, f| &acc * &f);
+        let id_product = (0..3)
+            .map(|c| &(&lro[c] + &Poly::from_coefficients_vec(vec![zero, beta * u.pow([c as u64])])) + &constant(gamma))
+            .fold(z.clone(), |acc, f| &acc * &f);
+        let l0 = interpolate(vec![one, zero, zero, zero]);
+        let numerator = &(&gate + &(&(&sigma_product - &id_product) * alpha))
+            + &(&(&l0 * &(&z - &constant(one))) * (alpha * alpha));
+        let (h, remainder) = numerator.divide_by_vanishing_poly(domain).unwrap();
+        assert!(remainder.is_zero(), "circuit is satisfied");
+        let mut coeffs = h.coeffs.clone();
+        coeffs.resize(3 * (N + 2), zero);
+        let h: Vec<Poly> = coeffs.chunks(N + 2).map(Poly::from_coefficients_slice).collect();
+        let h_commits: Vec<G1Affine> = h.iter().map(commit).collect();
+        let zeta_deps: Vec<[u8; 64]> = h_commits.iter().map(g1_bytes).collect();
+        let zeta = transcript.challenge("zeta", zeta_deps.iter().map(|b| &b[..]));
+
+        // Round 4: evaluations and the linearised polynomial
+        let [l, r, o] = [0, 1, 2].map(|c| lro[c].evaluate(&zeta));
+        let (s1, s2) = (s[0].evaluate(&zeta), s[1].evaluate(&zeta));
+        let zu = z.evaluate(&(zeta * domain.group_gen()));
+        let zh = zeta.pow([N as u64]) - one;
+        let lagrange_zero = zh / (zeta - one) * domain.size_inv();
+        let s1_coeff = (l + beta * s1 + gamma) * (r + beta * s2 + gamma) * beta * alpha * zu;
+        let s2_coeff = -(alpha
+            * (l + beta * zeta + gamma)
+            * (r + beta * u * zeta + gamma)
+            * (o + beta * u * u * zeta + gamma));
+        let zeta_n2 = zeta.pow([N as u64 + 2]);
+        let linearised = [
+            (&ql, l),
+            (&qr, r),
+            (&qm, l * r),
+            (&qo, o),
+            (&qk, one),
+            (&s[2], s1_coeff),
+            (&z, alpha * alpha * lagrange_zero + s2_coeff),
+            (&h[0], -zh),
+            (&h[1], -(zeta_n2 * zh)),
+            (&h[2], -(zeta_n2 * zeta_n2 * zh)),
+        ]
+        .into_iter()
+        .fold(Poly::zero(), |acc, (p, c)| &acc + &(p * c));
+        let claimed = [linearised.evaluate(&zeta), l, r, o, s1, s2];
+
+        // Round 5: fold the openings at ζ and open Z at ζω
+        let polys = [&linearised, &lro[0], &lro[1], &lro[2], &s[0], &s[1]];
+        let fold_data: Vec<Vec<u8>> = std::iter::once(fr_bytes(&zeta).to_vec())
+            .chain(polys.iter().map(|p| g1_bytes(&commit(p)).to_vec()))
+            .chain(claimed.iter().map(|v| fr_bytes(v).to_vec()))
+            .chain(std::iter::once(fr_bytes(&zu).to_vec()))
+            .collect();
+        let fold_gamma = Transcript::default().challenge("gamma", fold_data.iter().map(Vec::as_slice));
+        let folded = polys
+            .iter()
+            .rev()
+            .fold(Poly::zero(), |acc, p| &(&acc * fold_gamma) + *p);
+        let open = |p: &Poly, point: Fr| {
+            (G1Affine::generator() * ((p.evaluate(&tau) - p.evaluate(&point)) / (tau - point))).into_affine()
+        };
+
+        let mut proof = Vec::new();
+        for p in lro_commits.iter().chain([&z_commit]).chain(&h_commits) {
+            proof.extend(g1_bytes(p));
+        }
+        proof.extend(g1_bytes(&open(&folded, zeta)));
+        proof.extend((claimed.len() as u32).to_be_bytes());
+        claimed.iter().for_each(|v| proof.extend(fr_bytes(v)));
+        proof.extend(g1_bytes(&open(&z, zeta * domain.group_gen())));
+        proof.extend(fr_bytes(&zu));
+        proof.extend(0u32.to_be_bytes());
+        Fixture { proof, vk }
+    }
+
+    /// Uncompressed G2 point, Fp2 coordinates as A1 || A0
+    fn g2_bytes(point: &G2Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        [x.c1, x.c0, y.c1, y.c0]
+            .iter()
+            .flat_map(|c: &Fq| c.into_bigint().to_bytes_be())
+            .collect()
+    }
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn verifies_gnark_layout_proofs() {
+        let f = fixture(3, 5, 1234567);
+        assert_eq!(verify(&f.proof, &f.vk, &inputs(&["15"])), Verdict::Verified);
+        assert_eq!(key_info(&f.vk), KeyInfo { curve: "bn254", inputs: Some(1) });
+        assert!(check_inputs(&f.vk, &inputs(&["15"])).is_ok());
+        assert!(check_inputs(&f.vk, &inputs(&["15", "1"])).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_proofs() {
+        let f = fixture(3, 5, 1234567);
+        let other = fixture(3, 5, 7654321);
+        let failed = |verdict: Verdict| matches!(verdict, Verdict::Failed(_));
+
+        assert!(failed(verify(&f.proof, &f.vk, &inputs(&["16"]))), "tampered input");
+        assert!(failed(verify(&f.proof, &other.vk, &inputs(&["15"]))), "another setup's key");
+        assert!(failed(verify(&f.proof, &f.vk, &inputs(&[]))), "missing input");
+
+        // Bump the last byte of the linearised polynomial's claimed value
+        let mut tampered = f.proof.clone();
+        let claimed = 8 * 64 + 4;
+        tampered[claimed + 31] ^= 1;
+        assert!(failed(verify(&tampered, &f.vk, &inputs(&["15"]))), "tampered claimed value");
+    }
+
+    #[test]
+    fn undecodable_proofs_are_errors() {
+        let f = fixture(3, 5, 1234567);
+        let error = |verdict: Verdict| matches!(verdict, Verdict::Error(_));
+
+        assert!(error(verify(&f.proof[..f.proof.len() - 1], &f.vk, &inputs(&["15"]))), "truncated proof");
+        assert!(error(verify(&f.proof, &f.vk[1..], &inputs(&["15"]))), "truncated key");
+        assert!(error(verify(&f.proof, &f.vk, &inputs(&["fifteen"]))), "unreadable input");
+    }
+}
@@ -0,0 +1,212 @@
+//! STARK verifier for plonky2 FRI proofs
+//!
+//! The verification key is plonky2's serialized `VerifierCircuitData`
+//! (default gate serializer), which carries the circuit's gates and FRI
+//! parameters. The proof is a serialized `ProofWithPublicInputs`. Only the
+//! Goldilocks field is used by plonky2; the hash (Poseidon or Keccak) is
+//! found by trying each configuration in turn.
+
+use anyhow::{anyhow, bail, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field64, PrimeField64};
+use plonky2::plonk::circuit_data::VerifierCircuitData;
+use plonky2::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
+
+use super::{check_input_count, parse_uint, KeyInfo, Verdict};
+
+/// Extension degree used by plonky2's standard configurations
+const D: usize = 2;
+
+/// Minimum conjectured security (bits) accepted from the FRI parameters
+const MIN_SECURITY_BITS: usize = 80;
+
+/// Check public inputs against the Goldilocks field and the circuit
+pub fn check_inputs(vk: &[u8], inputs: &[String]) -> Result<()> {
+    parse_goldilocks(inputs)?;
+    match num_public_inputs(vk) {
+        Some(expected) => check_input_count(expected, inputs.len()),
+        None => Ok(()),
+    }
+}
+
+/// Curve and input count of a key
+pub fn key_info(vk: &[u8]) -> Option<KeyInfo> {
+    Some(KeyInfo { curve: "goldilocks", inputs: Some(num_public_inputs(vk)?) })
+}
+
+fn num_public_inputs(vk: &[u8]) -> Option<usize> {
+    if let Some(data) = load::<PoseidonGoldilocksConfig>(vk) {
+        Some(data.common.num_public_inputs)
+    } else {
+        load::<KeccakGoldilocksConfig>(vk).map(|data| data.common.num_public_inputs)
+    }
+}
+
+/// Verify a STARK proof
+pub fn verify(proof: &[u8], vk: &[u8], inputs: &[String]) -> Verdict {
+    if let Some(data) = load::<PoseidonGoldilocksConfig>(vk) {
+        check(&data, proof, inputs)
+    } else if let Some(data) = load::<KeccakGoldilocksConfig>(vk) {
+        check(&data, proof, inputs)
+    } else {
+        Verdict::Error("Malformed verification key: not plonky2 verifier data".to_string())
+    }
+}
+
+fn load<C: GenericConfig<D, F = GoldilocksField>>(vk: &[u8]) -> Option<VerifierCircuitData<GoldilocksField, C, D>> {
+    VerifierCircuitData::from_bytes(vk.to_vec(), &DefaultGateSerializer).ok()
+}
+
+fn check<C: GenericConfig<D, F = GoldilocksField>>(
+    data: &VerifierCircuitData<GoldilocksField, C, D>,
+    proof: &[u8],
+    inputs: &[String],
+) -> Verdict {
+    let decoded = ProofWithPublicInputs::<GoldilocksField, C, D>::from_bytes(proof.to_vec(), &data.common)
+        .map_err(|e| anyhow!("Malformed proof: {}", e))
+        .and_then(|proof| Ok((proof, parse_goldilocks(inputs)?)));
+    let (proof, expected) = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => return Verdict::Error(e.to_string()),
+    };
+
+    let fri = &data.common.config.fri_config;
+    let security = fri.rate_bits * fri.num_query_rounds + fri.proof_of_work_bits as usize;
+    if security < MIN_SECURITY_BITS {
+        return Verdict::Failed(format!(
+            "FRI parameters (blowup 2^{}, {} queries, {} PoW bits) give {} bits of security, below {}",
+            fri.rate_bits,
+            fri.num_query_rounds,
+            fri.proof_of_work_bits,
+            security,
+            MIN_SECURITY_BITS
+        ));
+    }
+
+    let actual: Vec<u64> = proof
+        .public_inputs
+        .iter()
+        .map(|x| x.to_canonical_u64())
+        .collect();
+    if expected != actual {
+        return Verdict::Failed(format!(
+            "Public inputs do not match the proof ({} submitted, {} in proof)",
+            expected.len(),
+            actual.len()
+        ));
+    }
+
+    match data.verify(proof) {
+        Ok(()) => Verdict::Verified,
+        Err(e) => Verdict::Failed(format!("FRI verification failed: {}", e)),
+    }
+}
+
+/// Parse public inputs as Goldilocks elements (decimal or 0x-hex)
+fn parse_goldilocks(inputs: &[String]) -> Result<Vec<u64>> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| match parse_uint(input).and_then(|value| u64::try_from(value).ok()) {
+            Some(value) if value < GoldilocksField::ORDER => Ok(value),
+            _ => bail!("Public input {} is not a Goldilocks element: {:?}", i, input),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+
+    type F = GoldilocksField;
+
+    struct Fixture {
+        proof: Vec<u8>,
+        vk: Vec<u8>,
+        /// The proof with its public input changed to 14
+        tampered: Vec<u8>,
+    }
+
+    /// Prove knowledge of `a` and `b` with `a * b = 15`
+    fn fixture<C: GenericConfig<D, F = F>>(config: CircuitConfig) -> Fixture {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let a = builder.add_virtual_target();
+        let b = builder.add_virtual_target();
+        let product = builder.mul(a, b);
+        builder.register_public_input(product);
+        let data = builder.build::<C>();
+
+        let mut witness = PartialWitness::new();
+        witness.set_target(a, F::from_canonical_u64(3));
+        witness.set_target(b, F::from_canonical_u64(5));
+        let proof = data.prove(witness).unwrap();
+        let mut tampered = proof.clone();
+        tampered.public_inputs[0] = F::from_canonical_u64(14);
+
+        Fixture {
+            proof: proof.to_bytes(),
+            vk: data.verifier_data().to_bytes(&DefaultGateSerializer).unwrap(),
+            tampered: tampered.to_bytes(),
+        }
+    }
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn failed(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Failed(_))
+    }
+
+    #[test]
+    fn verifies_plonky2_proofs() {
+        for f in [
+            fixture::<PoseidonGoldilocksConfig>(CircuitConfig::standard_recursion_config()),
+            fixture::<KeccakGoldilocksConfig>(CircuitConfig::standard_recursion_config()),
+        ] {
+            assert_eq!(verify(&f.proof, &f.vk, &inputs(&["15"])), Verdict::Verified);
+            assert_eq!(key_info(&f.vk), Some(KeyInfo { curve: "goldilocks", inputs: Some(1) }));
+            assert!(check_inputs(&f.vk, &inputs(&["15"])).is_ok());
+            assert!(check_inputs(&f.vk, &inputs(&["15", "1"])).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_proofs() {
+        let f = fixture::<PoseidonGoldilocksConfig>(CircuitConfig::standard_recursion_config());
+        assert!(failed(verify(&f.tampered, &f.vk, &inputs(&["14"]))));
+    }
+
+    #[test]
+    fn rejects_inputs_other_than_the_proofs() {
+        let f = fixture::<PoseidonGoldilocksConfig>(CircuitConfig::standard_recursion_config());
+        assert!(failed(verify(&f.proof, &f.vk, &inputs(&["16"]))));
+        assert!(failed(verify(&f.proof, &f.vk, &inputs(&[]))));
+    }
+
+    #[test]
+    fn rejects_keys_below_80_bits() {
+        let mut config = CircuitConfig::standard_recursion_config();
+        config.fri_config.num_query_rounds = 10;
+        let f = fixture::<PoseidonGoldilocksConfig>(config);
+        match verify(&f.proof, &f.vk, &inputs(&["15"])) {
+            Verdict::Failed(reason) => assert!(reason.contains("below 80"), "{}", reason),
+            verdict => panic!("weak key accepted: {:?}", verdict),
+        }
+    }
+
+    #[test]
+    fn undecodable_proofs_are_errors() {
+        let f = fixture::<PoseidonGoldilocksConfig>(CircuitConfig::standard_recursion_config());
+        let error = |verdict: Verdict| matches!(verdict, Verdict::Error(_));
+        assert!(error(verify(&f.proof[..f.proof.len() / 2], &f.vk, &inputs(&["15"]))));
+        assert!(error(verify(&f.proof, &f.vk[1..], &inputs(&["15"]))));
+        assert!(error(verify(&f.proof, &f.vk, &inputs(&["fifteen"]))));
+    }
+}
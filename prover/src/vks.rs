@@ -0,0 +1,249 @@
+//! Verification key registry
+//!
+//! Keys are registered once with `POST /api/v1/vks` and referenced from
+//! submissions by `vk_id` instead of being sent inline. A key's ID is the hex
+//! SHA-256 of its stored encoding, so the same key always gets the same ID,
+//! whatever format it was registered in. SNARKs whose key is registered,
+//! whether by reference or inline, are linked to it in the kernel.
+
+use axum::extract::{Path as AxumPath, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{D, T};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cord_to_string, decode_blob, error_response, handle_effects, is_term, peek_found, peek_json,
+    poke_kernel, string_to_cord, verify, Encoded, SharedState,
+};
+
+/// Key registration request
+#[derive(Debug, Deserialize)]
+pub struct Registration {
+    verification_key: Encoded,
+    proof_system: String,
+    /// `auto` (default), `arkworks`, `gnark` or `snarkjs`
+    format: Option<String>,
+    /// Name of the circuit the key belongs to
+    circuit: String,
+    version: Option<String>,
+}
+
+/// The parts of a registered key a submission needs
+#[derive(Debug, Deserialize)]
+pub struct VkRecord {
+    pub proof_system: String,
+    /// Base64 of the stored encoding
+    pub verification_key: String,
+}
+
+/// ID of a key in its stored encoding
+pub fn key_id(vk: &[u8]) -> String {
+    hex::encode(Sha256::digest(vk))
+}
+
+/// Register a verification key
+///
+/// Answers 201 with the new record, or 200 with the existing one if the key
+/// was registered before for the same proof system, circuit and version,
+/// and 409 if it was registered for others.
+pub async fn register(
+    State(state): State<SharedState>,
+    Json(registration): Json<Registration>,
+) -> Response {
+    if registration.verification_key.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Verification key is required");
+    }
+    if registration.proof_system.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Proof system is required");
+    }
+    if !is_term(&registration.proof_system) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Proof system must be lowercase letters, digits and hyphens, starting with a letter",
+        );
+    }
+    if registration.circuit.trim().is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "Circuit name is required");
+    }
+
+    let format = match registration.format.as_deref().unwrap_or("auto").parse::<verify::Format>() {
+        Ok(format) => format,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, &message),
+    };
+    let Some(blob) = decode_blob(&registration.verification_key) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "Invalid verification key: not Base64 or JSON",
+        );
+    };
+    let vk = match verify::normalize_vk(&registration.proof_system, format, blob) {
+        Ok(vk) => vk,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+
+    let mut poke_slab = NounSlab::new();
+
+    // [%register-vk id=@t system=@tas key=@t circuit=@t version=@t]
+    let id = string_to_cord(&mut poke_slab, &key_id(&vk));
+    let system = string_to_cord(&mut poke_slab, &registration.proof_system);
+    let key = string_to_cord(&mut poke_slab, &BASE64.encode(&vk));
+    let circuit = string_to_cord(&mut poke_slab, registration.circuit.trim());
+    let version = string_to_cord(&mut poke_slab, registration.version.as_deref().unwrap_or("").trim());
+    let cause = T(&mut poke_slab, &[D(b"register-vk" as &[u8]), id, system, key, circuit, version]);
+    poke_slab.set_root(cause);
+
+    let effects = match poke_kernel(&state, poke_slab, "Failed to register verification key").await {
+        Ok(effects) => effects,
+        Err(response) => return response,
+    };
+    handle_effects(effects).unwrap_or_else(|| {
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "No response from kernel")
+    })
+}
+
+/// List registered keys, oldest first, without the keys themselves
+pub async fn list(State(state): State<SharedState>) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"vks" as &[u8]), D(0)]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Not found").await
+}
+
+/// Get a registered key
+pub async fn get(State(state): State<SharedState>, AxumPath(id): AxumPath<String>) -> Response {
+    let mut peek_slab = NounSlab::new();
+    let id = string_to_cord(&mut peek_slab, &id);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"vk" as &[u8]), id, D(0)]);
+    peek_slab.set_root(path);
+
+    peek_json(&state, peek_slab, "Verification key not found").await
+}
+
+/// Look up a registered key
+pub async fn fetch(state: &SharedState, id: &str) -> Result<Option<VkRecord>, String> {
+    let mut peek_slab = NounSlab::new();
+    let id = string_to_cord(&mut peek_slab, id);
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"vk" as &[u8]), id, D(0)]);
+    peek_slab.set_root(path);
+
+    let Some(body) = peek_found(state, peek_slab).await? else {
+        return Ok(None);
+    };
+    cord_to_string(body)
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .map(Some)
+        .ok_or_else(|| "Invalid response from kernel".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::Compress;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::testing;
+
+    async fn register_key(state: &SharedState, registration: Value) -> (StatusCode, Value) {
+        let registration = serde_json::from_value(registration).expect("registration parses");
+        testing::json(register(State(state.clone()), Json(registration)).await).await
+    }
+
+    fn registration(vk: &[u8], proof_system: &str, circuit: &str) -> Value {
+        json!({
+            "verification_key": BASE64.encode(vk),
+            "proof_system": proof_system,
+            "circuit": circuit,
+            "version": "1.0.0",
+        })
+    }
+
+    #[tokio::test]
+    async fn registers_each_key_once() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let compressed = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let uncompressed = testing::groth16::<ark_bn254::Bn254>(Compress::No);
+
+        let (status, first) = register_key(&state, registration(&compressed.vk, "groth16", "product")).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", first);
+        assert_eq!(first["id"], key_id(&compressed.vk));
+        assert_eq!(first["circuit"], "product");
+        assert_eq!(first["verification_key"], BASE64.encode(&compressed.vk));
+
+        // The same key in another encoding is the same registration
+        let (status, again) = register_key(&state, registration(&uncompressed.vk, "groth16", "product")).await;
+        assert_eq!(status, StatusCode::OK, "{}", again);
+        assert_eq!(again, first);
+
+        let (status, body) = testing::json(get(State(state.clone()), AxumPath(key_id(&compressed.vk))).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, first);
+        let (_, body) = testing::json(list(State(state.clone())).await).await;
+        let listed = body["vks"].as_array().expect("vks is a list");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["id"], first["id"]);
+        assert!(listed[0].get("verification_key").is_none(), "lists leave keys out");
+
+        let (status, _) = testing::json(get(State(state.clone()), AxumPath("0".repeat(64))).await).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn refuses_to_register_a_key_twice_differently() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let (status, _) = register_key(&state, registration(&fixture.vk, "groth16", "product")).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let mut other_version = registration(&fixture.vk, "groth16", "product");
+        other_version["version"] = json!("2.0.0");
+        // PLONK keeps bytes as submitted, so the ID is the same
+        let cases = [
+            registration(&fixture.vk, "groth16", "other"),
+            other_version,
+            registration(&fixture.vk, "plonk", "product"),
+        ];
+        for case in cases {
+            let (status, body) = register_key(&state, case.clone()).await;
+            assert_eq!(status, StatusCode::CONFLICT, "{} gave {}", case, body);
+        }
+        let (_, body) = testing::json(get(State(state.clone()), AxumPath(key_id(&fixture.vk))).await).await;
+        assert_eq!((&body["proof_system"], &body["circuit"]), (&json!("groth16"), &json!("product")));
+    }
+
+    #[tokio::test]
+    async fn refuses_bad_registrations() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let valid = registration(&fixture.vk, "groth16", "product");
+        let with = |field: &str, value: Value| {
+            let mut registration = valid.clone();
+            registration[field] = value;
+            registration
+        };
+        let cases = [
+            with("verification_key", json!("")),
+            with("verification_key", json!("not base64!")),
+            with("verification_key", json!(BASE64.encode(b"not a key"))),
+            with("proof_system", json!("")),
+            with("proof_system", json!("Groth16")),
+            with("proof_system", json!("groth 16")),
+            with("proof_system", json!("16groth")),
+            with("proof_system", json!("groth16\u{0}")),
+            with("circuit", json!(" ")),
+            with("format", json!("bellman")),
+        ];
+        for case in cases {
+            let (status, body) = register_key(&state, case.clone()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{} gave {}", case, body);
+        }
+        let (_, body) = testing::json(list(State(state.clone())).await).await;
+        assert_eq!(body["vks"], json!([]));
+    }
+}
@@ -0,0 +1,637 @@
+//! Webhook callbacks
+//!
+//! When a verification run finishes (`verified`, `failed` or `error`) the
+//! kernel emits `[%completed id=@ud callback=(unit @t) payload=@t]`. The
+//! driver POSTs the payload, the same JSON as the `status` event, to the
+//! SNARK's own callback URL and to the global webhook URL, if configured.
+//!
+//! Requests are signed: `X-Prover-Signature: sha256=<hex>` is the
+//! HMAC-SHA256 of `<timestamp>.<body>` keyed with the webhook secret, where
+//! `X-Prover-Timestamp` carries the Unix timestamp. Failed deliveries are
+//! retried with exponential backoff, and every attempt is logged in the
+//! kernel for `GET /api/v1/snark/:id/deliveries`. On boot, deliveries the
+//! log shows were cut short by a restart are resumed.
+//!
+//! Webhooks only go to public addresses, so a callback URL cannot reach
+//! the server's own network or a cloud metadata service. Hosts on the
+//! configured allow-list are exempt. Names are checked each time they are
+//! resolved, and redirects are not followed.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use hyper::client::connect::dns::Name;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{Noun, D, T};
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::{
+    cord_to_string, handle_effects, list_items, loobean, peek_found, poke_kernel, string_to_cord,
+    unit, AppState, SharedState,
+};
+
+/// Time allowed for the receiver to answer one request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default wait before the first retry
+pub const FIRST_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Webhook configuration
+pub struct Settings {
+    /// Notified of every finished verification
+    pub url: Option<String>,
+    /// Signing key; callbacks are refused without one
+    pub secret: Option<String>,
+    /// Tries per delivery
+    pub attempts: u32,
+    /// Hosts allowed to be private or local addresses, as written in URLs
+    pub allow_hosts: Vec<String>,
+    /// Wait before the first retry; doubled for each one after
+    pub backoff: Duration,
+}
+
+/// A finished verification to announce
+#[derive(Debug)]
+pub struct Completion {
+    id: u64,
+    callback: Option<String>,
+    payload: String,
+}
+
+/// A finished SNARK with webhooks, as the kernel's delivery log has it
+#[derive(Debug)]
+struct Undelivered {
+    id: u64,
+    callback: Option<String>,
+    payload: String,
+    /// URL and HTTP status of each attempt, oldest first
+    attempts: Vec<(String, Option<u16>)>,
+}
+
+/// A delivery a restart cut short, from its next attempt on
+#[derive(Debug)]
+struct Owed {
+    id: u64,
+    url: String,
+    payload: String,
+    attempt: u32,
+}
+
+/// Check a webhook URL is absolute http(s) to a public or allowed host
+///
+/// Names pass here unless they are `localhost`; their addresses are
+/// checked when a delivery resolves them.
+pub fn check_url(url: &str, allow_hosts: &[String]) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    match parsed.scheme() {
+        "http" | "https" => {}
+        scheme => return Err(format!("unsupported scheme {:?}; use http or https", scheme)),
+    }
+    let host = parsed.host_str().ok_or("missing host")?;
+    if is_allowed(allow_hosts, host) {
+        return Ok(());
+    }
+    let name = host.trim_end_matches('.').to_ascii_lowercase();
+    if name == "localhost" || name.ends_with(".localhost") {
+        return Err(format!("{} is a local host", host));
+    }
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) if !is_public(ip) => Err(format!("{} is not a public address", ip)),
+        _ => Ok(()),
+    }
+}
+
+fn is_allowed(allow_hosts: &[String], host: &str) -> bool {
+    allow_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host))
+}
+
+/// Whether an address is on the public internet
+///
+/// Loopback, private, link-local (where cloud metadata services live),
+/// shared, unspecified, broadcast, multicast, documentation and reserved
+/// ranges are not.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// Resolver for webhook requests that drops non-public addresses of
+/// names not on the allow-list
+///
+/// Checking at connection time means a name cannot pass `check_url` and
+/// then be pointed at a private address.
+struct PublicResolver {
+    allow_hosts: Vec<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allowed = is_allowed(&self.allow_hosts, name.as_str());
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allowed || is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// HTTP client for deliveries
+fn client(allow_hosts: &[String]) -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver { allow_hosts: allow_hosts.to_vec() }))
+        .build()
+        .expect("webhook client builds")
+}
+
+/// Parse a `[%completed id=@ud callback=(unit @t) payload=@t]` effect
+fn parse(effect: Noun) -> Option<Completion> {
+    let cell = effect.as_cell().ok()?;
+    if !cell.head().eq_bytes(b"completed") {
+        return None;
+    }
+    let fields = cell.tail().as_cell().ok()?;
+    let rest = fields.tail().as_cell().ok()?;
+    let callback = match rest.head().as_cell() {
+        Ok(some) => Some(cord_to_string(some.tail())?),
+        Err(_) => None,
+    };
+    Some(Completion {
+        id: fields.head().as_atom().ok()?.as_u64().ok()?,
+        callback,
+        payload: cord_to_string(rest.tail())?,
+    })
+}
+
+/// Queue the completions among a poke's effects for delivery
+pub fn queue<'a>(
+    completions: &mpsc::UnboundedSender<Completion>,
+    effects: impl IntoIterator<Item = &'a Noun>,
+) {
+    for completion in effects.into_iter().filter_map(|&effect| parse(effect)) {
+        // Only fails once the dispatcher has stopped at shutdown
+        let _ = completions.send(completion);
+    }
+}
+
+/// Resume cut-short deliveries, then start delivering queued completions
+///
+/// Resumption finishes before this returns, so run it before the workers
+/// start finishing verifications.
+pub async fn spawn(state: SharedState, mut completions: mpsc::UnboundedReceiver<Completion>) {
+    let client = client(&state.webhook.allow_hosts);
+    if let Some(secret) = &state.webhook.secret {
+        match owed(&state).await {
+            Ok(owed) => {
+                if !owed.is_empty() {
+                    log::info!("Resuming {} webhook deliveries", owed.len());
+                }
+                for owed in owed {
+                    tokio::spawn(deliver(
+                        state.clone(),
+                        client.clone(),
+                        secret.clone(),
+                        owed.id,
+                        owed.url,
+                        owed.payload,
+                        owed.attempt,
+                    ));
+                }
+            }
+            Err(e) => log::error!("Error listing webhook deliveries to resume: {}", e),
+        }
+    }
+
+    tokio::spawn(async move {
+        while let Some(completion) = completions.recv().await {
+            let Some(secret) = state.webhook.secret.clone() else {
+                continue;
+            };
+            for url in targets(completion.callback.as_ref(), state.webhook.url.as_ref()) {
+                tokio::spawn(deliver(
+                    state.clone(),
+                    client.clone(),
+                    secret.clone(),
+                    completion.id,
+                    url,
+                    completion.payload.clone(),
+                    1,
+                ));
+            }
+        }
+    });
+}
+
+/// Where a completion goes: the SNARK's callback, then the global URL
+fn targets(callback: Option<&String>, global: Option<&String>) -> Vec<String> {
+    let mut targets: Vec<String> = callback.into_iter().chain(global).cloned().collect();
+    targets.dedup();
+    targets
+}
+
+/// Deliveries a restart cut short, from the kernel's delivery log
+///
+/// A target is owed its remaining attempts until it accepts the payload.
+/// One with no attempt logged was stopped before its first, and is owed
+/// every attempt.
+async fn owed(state: &AppState) -> Result<Vec<Owed>, String> {
+    let mut peek_slab = NounSlab::new();
+    let global = loobean(state.webhook.url.is_some());
+    let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"undelivered" as &[u8]), global, D(0)]);
+    peek_slab.set_root(path);
+
+    let list = peek_found(state, peek_slab)
+        .await?
+        .ok_or_else(|| "Invalid response from kernel".to_string())?;
+    let mut owed = Vec::new();
+    for item in list_items(list) {
+        let snark = parse_undelivered(item).ok_or("Invalid response from kernel")?;
+        for url in targets(snark.callback.as_ref(), state.webhook.url.as_ref()) {
+            let tried: Vec<Option<u16>> = snark
+                .attempts
+                .iter()
+                .filter(|(to, _)| *to == url)
+                .map(|&(_, code)| code)
+                .collect();
+            if tried.iter().flatten().any(|&code| accepted(code)) {
+                continue;
+            }
+            let attempt = tried.len() as u32 + 1;
+            if attempt <= state.webhook.attempts {
+                owed.push(Owed { id: snark.id, url, payload: snark.payload.clone(), attempt });
+            }
+        }
+    }
+    Ok(owed)
+}
+
+/// Parse `[id=@ud callback=(unit @t) payload=@t attempts=(list [url=@t code=(unit @ud)])]`
+fn parse_undelivered(item: Noun) -> Option<Undelivered> {
+    let cell = item.as_cell().ok()?;
+    let id = cell.head().as_atom().ok()?.as_u64().ok()?;
+    let rest = cell.tail().as_cell().ok()?;
+    let callback = match rest.head().as_cell() {
+        Ok(some) => Some(cord_to_string(some.tail())?),
+        Err(_) => None,
+    };
+    let rest = rest.tail().as_cell().ok()?;
+    let payload = cord_to_string(rest.head())?;
+    let attempts = list_items(rest.tail())
+        .into_iter()
+        .map(|attempt| {
+            let attempt = attempt.as_cell().ok()?;
+            let code = match attempt.tail().as_cell() {
+                Ok(some) => Some(u16::try_from(some.tail().as_atom().ok()?.as_u64().ok()?).ok()?),
+                Err(_) => None,
+            };
+            Some((cord_to_string(attempt.head())?, code))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Undelivered { id, callback, payload, attempts })
+}
+
+/// Whether a response status means the receiver took the payload
+fn accepted(code: u16) -> bool {
+    (200..300).contains(&code)
+}
+
+/// POST a payload until it is accepted or the attempts run out
+///
+/// Attempts are numbered from `first`, which is 1 unless a restart cut the
+/// delivery short. URLs stored before they had to be public are refused
+/// here, logged as one failed attempt.
+async fn deliver(
+    state: SharedState,
+    client: reqwest::Client,
+    secret: String,
+    id: u64,
+    url: String,
+    payload: String,
+    first: u32,
+) {
+    if let Err(e) = check_url(&url, &state.webhook.allow_hosts) {
+        log::warn!("Not delivering webhook for SNARK #{} to {}: {}", id, url, e);
+        record(&state, id, &url, first, None, Some(&e)).await;
+        return;
+    }
+    let attempts = state.webhook.attempts;
+    for attempt in first..=attempts {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let result = client
+            .post(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header("X-Prover-Event", "verification.completed")
+            .header("X-Prover-Timestamp", timestamp.to_string())
+            .header("X-Prover-Signature", format!("sha256={}", sign(&secret, timestamp, &payload)))
+            .body(payload.clone())
+            .send()
+            .await;
+
+        let (code, error) = match result {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        record(&state, id, &url, attempt, code, error.as_deref()).await;
+        if code.is_some_and(accepted) {
+            return;
+        }
+        if attempt < attempts {
+            tokio::time::sleep(backoff(state.webhook.backoff, attempt)).await;
+        }
+    }
+    log::warn!("Gave up delivering webhook for SNARK #{} to {} after {} attempts", id, url, attempts);
+}
+
+/// Wait after failed attempt number `attempt`
+fn backoff(first: Duration, attempt: u32) -> Duration {
+    first
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(MAX_BACKOFF)
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>`
+fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Poke `%record-delivery` with the outcome of one attempt
+async fn record(
+    state: &AppState,
+    id: u64,
+    url: &str,
+    attempt: u32,
+    code: Option<u16>,
+    error: Option<&str>,
+) {
+    let mut poke_slab = NounSlab::new();
+
+    // [%record-delivery id=@ud url=@t attempt=@ud code=(unit @ud) error=(unit @t)]
+    let url = string_to_cord(&mut poke_slab, url);
+    let code = unit(&mut poke_slab, code.map(|code| D(u64::from(code))));
+    let error = error.map(|e| string_to_cord(&mut poke_slab, e));
+    let error = unit(&mut poke_slab, error);
+    let cause = T(&mut poke_slab, &[
+        D(b"record-delivery" as &[u8]),
+        D(id),
+        url,
+        D(u64::from(attempt)),
+        code,
+        error,
+    ]);
+    poke_slab.set_root(cause);
+
+    let message = format!("Failed to record webhook delivery for SNARK #{}", id);
+    if let Ok(effects) = poke_kernel(state, poke_slab, &message).await {
+        handle_effects(effects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+
+    use super::*;
+    use crate::testing::{self, Segment};
+
+    const SECRET: &str = "test-secret";
+    const PAYLOAD: &str = r#"{"id":1}"#;
+    const BACKOFF: Duration = Duration::from_millis(50);
+
+    /// A request as the receiver saw it
+    #[derive(Debug, Clone)]
+    struct Received {
+        at: Instant,
+        timestamp: String,
+        signature: String,
+        body: String,
+    }
+
+    type Log = Arc<Mutex<Vec<Received>>>;
+
+    fn settings(url: Option<String>, attempts: u32) -> Settings {
+        Settings {
+            url,
+            secret: Some(SECRET.to_string()),
+            attempts,
+            allow_hosts: vec!["127.0.0.1".to_string()],
+            backoff: BACKOFF,
+        }
+    }
+
+    /// Start a receiver on a local port that answers its first `failures`
+    /// requests with 500 and the rest with 200
+    async fn receiver(failures: usize) -> (String, Log) {
+        let log = Log::default();
+        let app = axum::Router::new()
+            .route("/hook", axum::routing::post(receive))
+            .with_state((log.clone(), failures));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, log)
+    }
+
+    async fn receive(
+        State((log, failures)): State<(Log, usize)>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let mut log = log.lock().unwrap();
+        log.push(Received {
+            at: Instant::now(),
+            timestamp: header("x-prover-timestamp"),
+            signature: header("x-prover-signature"),
+            body,
+        });
+        if log.len() <= failures {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    /// Attempt numbers and status codes in a SNARK's delivery log
+    async fn logged(state: &AppState, id: u64) -> Vec<(u64, Option<u64>)> {
+        let mut app = state.nockapp.write().await;
+        let log = testing::peek_json(&mut app, &[Segment::Text("deliveries"), Segment::Number(id)]).await;
+        log["deliveries"]
+            .as_array()
+            .expect("deliveries")
+            .iter()
+            .map(|d| (d["attempt"].as_u64().unwrap(), d["status_code"].as_u64()))
+            .collect()
+    }
+
+    #[test]
+    fn refuses_private_addresses() {
+        for bad in [
+            "http://localhost/hook",
+            "http://api.localhost./hook",
+            "http://127.0.0.1:8080/hook",
+            "http://127.1/hook",
+            "http://0.0.0.0/hook",
+            "http://10.1.2.3/hook",
+            "http://172.16.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://100.64.0.1/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:10.0.0.1]/hook",
+            "ftp://example.com/hook",
+        ] {
+            assert!(check_url(bad, &[]).is_err(), "{} should be refused", bad);
+        }
+        for good in ["https://example.com/hook", "http://93.184.215.14/hook", "http://[2606:4700::1]/"] {
+            assert!(check_url(good, &[]).is_ok(), "{} should be accepted", good);
+        }
+        let allowed = ["LOCALHOST".to_string(), "10.1.2.3".to_string()];
+        assert!(check_url("http://localhost:9000/hook", &allowed).is_ok());
+        assert!(check_url("http://10.1.2.3/hook", &allowed).is_ok());
+    }
+
+    #[tokio::test]
+    async fn resolver_drops_private_addresses() {
+        let localhost = || "localhost".parse::<Name>().unwrap();
+        let refused = PublicResolver { allow_hosts: vec![] }.resolve(localhost()).await;
+        assert!(refused.is_err(), "localhost resolves only to loopback");
+
+        let allowed = PublicResolver { allow_hosts: vec!["localhost".to_string()] };
+        let addrs: Vec<SocketAddr> = allowed.resolve(localhost()).await.expect("allowed name resolves").collect();
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+    }
+
+    #[tokio::test]
+    async fn signs_and_retries_deliveries() {
+        let (url, received) = receiver(1).await;
+        let (state, _dir) = testing::state(settings(None, 3)).await;
+        let id = testing::submit(&mut *state.nockapp.write().await, "alice", "", "digest-1").await;
+
+        let client = client(&state.webhook.allow_hosts);
+        deliver(state.clone(), client, SECRET.into(), id, url.clone(), PAYLOAD.into(), 1).await;
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2, "one failure, then success");
+        assert!(received[1].at - received[0].at >= BACKOFF, "retry waits out the backoff");
+        for request in &received {
+            assert_eq!(request.body, PAYLOAD);
+            let hex = request.signature.strip_prefix("sha256=").expect("sha256= signature");
+            let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+            mac.update(format!("{}.{}", request.timestamp, request.body).as_bytes());
+            mac.verify_slice(&hex::decode(hex).unwrap()).expect("signature matches");
+        }
+        assert_eq!(logged(&state, id).await, [(1, Some(500)), (2, Some(200))]);
+    }
+
+    #[tokio::test]
+    async fn resumes_cut_short_deliveries() {
+        let (url, received) = receiver(0).await;
+        let callback = format!("{}?callback", url);
+        let (state, _dir) = testing::state(settings(Some(url.clone()), 3)).await;
+        let mut app = state.nockapp.write().await;
+        let delivered = testing::submit(&mut app, "alice", "", "digest-1").await;
+        let cut_short = testing::submit(&mut app, "bob", "", "digest-2").await;
+        let never_tried = testing::submit(&mut app, "carol", "", "digest-3").await;
+        let half_done = testing::submit_with_callback(&mut app, "erin", "", "digest-5", Some(&callback)).await;
+        let unfinished = testing::submit(&mut app, "dave", "", "digest-4").await;
+        for id in [delivered, cut_short, never_tried, half_done] {
+            for status in ["verifying", "verified"] {
+                let mut slab = NounSlab::new();
+                let status = string_to_cord(&mut slab, status);
+                let actor = string_to_cord(&mut slab, "verifier");
+                let cause = T(&mut slab, &[D(b"update-status" as &[u8]), D(id), status, actor, D(0), D(0)]);
+                slab.set_root(cause);
+                let (code, body) = testing::poke(&mut app, slab).await.expect("kernel answers");
+                assert_eq!(code, 200, "{}", body);
+            }
+        }
+
+        // Without a global URL, only SNARKs with a callback or an attempt
+        // can be owed anything
+        let listed = testing::peek(&mut app, &[Segment::Text("undelivered"), Segment::Number(1)])
+            .await
+            .expect("kernel lists undelivered SNARKs");
+        let listed: Vec<u64> = list_items(listed).into_iter().map(|item| parse_undelivered(item).unwrap().id).collect();
+        assert_eq!(listed, [half_done]);
+        drop(app);
+
+        record(&state, delivered, &url, 1, Some(204), None).await;
+        record(&state, cut_short, &url, 1, None, Some("connection reset")).await;
+        record(&state, half_done, &callback, 1, Some(200), None).await;
+
+        let mut owed: Vec<(u64, String, u32)> = owed(&state)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|o| (o.id, o.url, o.attempt))
+            .collect();
+        owed.sort();
+        let mut expected = vec![(cut_short, url.clone(), 2), (never_tried, url.clone(), 1), (half_done, url.clone(), 1)];
+        expected.sort();
+        assert_eq!(owed, expected);
+        assert!(!owed.iter().any(|(id, ..)| *id == unfinished));
+
+        let client = client(&state.webhook.allow_hosts);
+        deliver(state.clone(), client, SECRET.into(), cut_short, url.clone(), PAYLOAD.into(), 2).await;
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(logged(&state, cut_short).await, [(1, None), (2, Some(200))]);
+    }
+}
@@ -0,0 +1,296 @@
+//! Background verification
+//!
+//! Submissions are verified off the request path. `submit_snark` reserves a
+//! slot in a bounded queue before storing a SNARK and sends its ID once the
+//! kernel has assigned one; a dispatcher runs queued jobs with a limit on
+//! concurrency and a timeout on each. A job that times out keeps its slot
+//! until its verifier thread finishes, so slow proofs cannot pile up more
+//! threads than there are workers. On boot, entries still `%pending` or
+//! left `%verifying` by a previous run are queued again.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use nockapp::noun::slab::NounSlab;
+use nockapp::noun::{D, T};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+use crate::verify::{self, Verdict};
+use crate::{
+    cord_to_string, list_query, peek_found, set_status, ListParams, SharedState, SnarkDetails,
+    SnarkList, MAX_PAGE_SIZE, VERIFIER_ACTOR,
+};
+
+/// Start the dispatcher for jobs sent on the state's queue
+pub fn spawn(state: SharedState, mut jobs: mpsc::Receiver<u64>, workers: usize, timeout: Duration) {
+    let permits = Arc::new(Semaphore::new(workers));
+    tokio::spawn(async move {
+        while let Some(id) = jobs.recv().await {
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let state = state.clone();
+            tokio::spawn(async move { process(&state, id, timeout, permit).await });
+        }
+    });
+}
+
+/// Queue every SNARK whose verification has not finished
+///
+/// Interrupted runs go first. Waits for queue space rather than dropping
+/// jobs, so it runs in its own task.
+pub fn recover(state: SharedState) {
+    tokio::spawn(async move {
+        let mut queued = 0;
+        for status in ["verifying", "pending"] {
+            let ids = match unfinished(&state, status).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    log::error!("Error listing {} SNARKs to verify: {}", status, e);
+                    continue;
+                }
+            };
+            for id in ids {
+                if state.jobs.send(id).await.is_err() {
+                    return;
+                }
+                queued += 1;
+            }
+        }
+        if queued > 0 {
+            log::info!("Queued {} unfinished SNARKs for verification", queued);
+        }
+    });
+}
+
+/// IDs of the SNARKs with `status`, paging through the list query
+async fn unfinished(state: &SharedState, status: &str) -> Result<Vec<u64>, String> {
+    let mut ids = Vec::new();
+    let mut cursor = None;
+    loop {
+        let params = ListParams {
+            status: Some(status.to_string()),
+            limit: Some(MAX_PAGE_SIZE),
+            cursor,
+            ..Default::default()
+        };
+        let mut peek_slab = NounSlab::new();
+        let query = list_query(&mut peek_slab, &params)?;
+        let path = T(&mut peek_slab, &[D(b"x" as &[u8]), D(b"snarks" as &[u8]), query]);
+        peek_slab.set_root(path);
+
+        let body = peek_found(state, peek_slab).await?.and_then(cord_to_string);
+        let page: SnarkList = body
+            .and_then(|body| serde_json::from_str(&body).ok())
+            .ok_or("Invalid response from kernel")?;
+
+        ids.extend(page.snarks.iter().map(|snark| snark.id));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(ids),
+        }
+    }
+}
+
+/// Verify one SNARK and record the outcome
+///
+/// `permit` is the job's worker slot, released when the verifier finishes.
+async fn process(state: &SharedState, id: u64, timeout: Duration, permit: OwnedSemaphorePermit) {
+    let Some(snark) = fetch(state, id).await else {
+        log::warn!("SNARK #{} is gone, skipping verification", id);
+        return;
+    };
+    if !verify::supports(&snark.proof_system) {
+        return;
+    }
+    match snark.status.as_str() {
+        "pending" => {
+            if !transition(state, id, "verifying", None).await {
+                return;
+            }
+        }
+        // Interrupted by a restart
+        "verifying" => {}
+        // Already finished, e.g. queued twice
+        _ => return,
+    }
+
+    let verdict = match (BASE64.decode(&snark.proof), BASE64.decode(&snark.verification_key)) {
+        (Ok(proof), Ok(vk)) => {
+            let run = run_verifier(snark.proof_system, proof, vk, snark.public_inputs, permit);
+            match tokio::time::timeout(timeout, run).await {
+                Ok(verdict) => verdict,
+                Err(_) => Verdict::Error(format!(
+                    "Verification timed out after {}s",
+                    timeout.as_secs()
+                )),
+            }
+        }
+        _ => Verdict::Error("Stored proof or verification key is not valid Base64".to_string()),
+    };
+    transition(state, id, verdict.status(), verdict.reason()).await;
+}
+
+/// Peek the full entry of a SNARK
+async fn fetch(state: &SharedState, id: u64) -> Option<SnarkDetails> {
+    let mut peek_slab = NounSlab::new();
+    let path = T(&mut peek_slab, &[
+        D(b"x" as &[u8]),
+        D(b"snark" as &[u8]),
+        D(id),
+        D(0),
+    ]);
+    peek_slab.set_root(path);
+
+    match peek_found(state, peek_slab).await {
+        Ok(body) => serde_json::from_str(&cord_to_string(body?)?).ok(),
+        Err(e) => {
+            log::error!("Error reading SNARK #{}: {:?}", id, e);
+            None
+        }
+    }
+}
+
+/// Poke a status change, returning whether the kernel accepted it
+///
+/// A refusal means someone else changed the SNARK meanwhile, e.g. through
+/// the status endpoint, so the job stops.
+async fn transition(state: &SharedState, id: u64, status: &str, reason: Option<&str>) -> bool {
+    match set_status(state, id, status, VERIFIER_ACTOR, reason, None).await {
+        Some(response) if response.status().is_success() => true,
+        _ => {
+            log::warn!("SNARK #{} could not move to {}", id, status);
+            false
+        }
+    }
+}
+
+/// Run the verifier for a proof system off the async runtime
+///
+/// A timed-out verifier thread cannot be interrupted; it runs to completion
+/// and its verdict is dropped. It holds `permit` until then, so it still
+/// counts against the worker limit.
+async fn run_verifier(
+    proof_system: String,
+    proof: Vec<u8>,
+    vk: Vec<u8>,
+    inputs: Vec<String>,
+    permit: OwnedSemaphorePermit,
+) -> Verdict {
+    let task = tokio::task::spawn_blocking(move || {
+        let verdict = verify::verify(&proof_system, &proof, &vk, &inputs);
+        drop(permit);
+        verdict
+    });
+    match task.await {
+        Ok(Some(verdict)) => verdict,
+        Ok(None) => Verdict::Error("No verifier for this proof system".to_string()),
+        Err(e) => Verdict::Error(format!("Verifier crashed: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::Compress;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::Json;
+
+    use super::*;
+    use crate::testing;
+    use crate::{count_snarks, submit_snark, SnarkSubmission};
+
+    /// A valid Groth16 submission from alice
+    fn submission() -> SnarkSubmission {
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        serde_json::from_value(serde_json::json!({
+            "proof": BASE64.encode(fixture.proof),
+            "verification_key": BASE64.encode(fixture.vk),
+            "public_inputs": ["15"],
+            "proof_system": "groth16",
+            "submitter": "alice",
+        }))
+        .unwrap()
+    }
+
+    async fn submit(state: &SharedState) -> (StatusCode, serde_json::Value) {
+        let response = submit_snark(State(state.clone()), None, HeaderMap::new(), Json(submission())).await;
+        testing::json(response).await
+    }
+
+    async fn count(state: &SharedState) -> serde_json::Value {
+        testing::json(count_snarks(State(state.clone())).await).await.1["count"].clone()
+    }
+
+    async fn worker_slot() -> OwnedSemaphorePermit {
+        Arc::new(Semaphore::new(1)).acquire_owned().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn full_queue_refuses_submissions() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 1).await;
+        state.jobs.try_send(u64::MAX).expect("queue has room");
+
+        let (status, body) = submit(&state).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+        assert_eq!(count(&state).await, 0, "nothing is stored without a place in the queue");
+
+        assert_eq!(jobs.recv().await, Some(u64::MAX));
+        let (status, body) = submit(&state).await;
+        assert!(status.is_success(), "{}", body);
+        assert_eq!(jobs.recv().await, body["id"].as_u64());
+    }
+
+    #[tokio::test]
+    async fn verifies_queued_snarks() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 1).await;
+        let (_, body) = submit(&state).await;
+        let id = jobs.recv().await.expect("submission is queued");
+        assert_eq!(body["id"].as_u64(), Some(id));
+
+        process(&state, id, Duration::from_secs(60), worker_slot().await).await;
+        let snark = fetch(&state, id).await.expect("SNARK is stored");
+        assert_eq!(snark.status, "verified");
+    }
+
+    #[tokio::test]
+    async fn timeouts_end_in_error() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 1).await;
+        submit(&state).await;
+        let id = jobs.recv().await.expect("submission is queued");
+
+        process(&state, id, Duration::ZERO, worker_slot().await).await;
+        let snark = fetch(&state, id).await.expect("SNARK is stored");
+        assert_eq!(snark.status, "error");
+        let reason = snark.error_message.unwrap_or_default();
+        assert!(reason.contains("timed out"), "{}", reason);
+    }
+
+    #[tokio::test]
+    async fn boot_requeues_unfinished_snarks() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 8).await;
+        let mut ids = Vec::new();
+        {
+            let mut app = state.nockapp.write().await;
+            for digest in ["pending", "verifying", "verified"] {
+                ids.push(testing::submit(&mut app, "alice", "", digest).await);
+            }
+        }
+        let [pending, verifying, verified] = ids[..] else { unreachable!() };
+        assert!(transition(&state, verifying, "verifying", None).await);
+        assert!(transition(&state, verified, "verifying", None).await);
+        assert!(transition(&state, verified, "verified", None).await);
+
+        recover(state.clone());
+        let mut queued = Vec::new();
+        for _ in 0..2 {
+            let id = tokio::time::timeout(Duration::from_secs(5), jobs.recv()).await.expect("job is queued");
+            queued.extend(id);
+        }
+        assert_eq!(queued, [verifying, pending], "interrupted runs go first");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(jobs.try_recv().is_err(), "finished SNARKs are not queued");
+    }
+}
@@ -0,0 +1,257 @@
+//! WebSocket API
+//!
+//! One connection to submit SNARKs and follow their progress. Clients send
+//! JSON text messages tagged by `type`:
+//!
+//! - `{"type":"submit", ...}` with the fields of a REST submission; the reply
+//!   carries the REST status code and body, and the new ID is subscribed to
+//! - `{"type":"subscribe","ids":[...]}` / `{"type":"unsubscribe","ids":[...]}`
+//!
+//! Events for subscribed IDs are pushed as `{"type":"event","event":...,"data":...}`
+//! with the same names and data as the SSE stream. Any SNARK may be
+//! subscribed to, whoever submitted it.
+
+use std::collections::HashSet;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use axum::{Extension, Json};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::auth::Principal;
+use crate::events::Event;
+use crate::{submit_snark, SharedState, SnarkSubmission};
+
+/// Largest response body forwarded to a client
+const MAX_REPLY_BYTES: usize = 1 << 20;
+
+/// Message from a client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Submit(Box<SnarkSubmission>),
+    Subscribe { ids: Vec<u64> },
+    Unsubscribe { ids: Vec<u64> },
+}
+
+/// Upgrade `GET /api/v1/ws` to a WebSocket session
+///
+/// Submissions over the session are made as the principal whose key
+/// opened it.
+pub async fn upgrade(
+    ws: WebSocketUpgrade,
+    State(state): State<SharedState>,
+    principal: Option<Extension<Principal>>,
+) -> Response {
+    ws.on_upgrade(move |socket| session(socket, state, principal))
+}
+
+async fn session(mut socket: WebSocket, state: SharedState, principal: Option<Extension<Principal>>) {
+    let mut events = state.events.subscribe();
+    let mut subscribed = HashSet::new();
+
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => handle(&state, principal.clone(), &text, &mut subscribed).await,
+                Some(Ok(Message::Binary(_))) => error("Expected a JSON text message"),
+                // Pings are answered by axum
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+            },
+            event = events.recv() => match event {
+                Ok(event) if subscribed.contains(&event.id) => {
+                    if event.name == "deleted" {
+                        subscribed.remove(&event.id);
+                    }
+                    push(&event)
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => json!({ "type": "lagged", "missed": missed }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+        if socket.send(Message::Text(reply.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Act on a client message and build the reply
+async fn handle(
+    state: &SharedState,
+    principal: Option<Extension<Principal>>,
+    text: &str,
+    subscribed: &mut HashSet<u64>,
+) -> Value {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => return error(&format!("Invalid message: {}", e)),
+    };
+
+    match message {
+        ClientMessage::Submit(submission) => {
+            // Same path as POST /api/v1/snark, so both APIs always agree
+            let response =
+                submit_snark(State(state.clone()), principal, HeaderMap::new(), Json(*submission))
+                    .await;
+            let status = response.status();
+            let body = match axum::body::to_bytes(response.into_body(), MAX_REPLY_BYTES).await {
+                Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+                Err(_) => Value::Null,
+            };
+            if status.is_success() {
+                if let Some(id) = body.get("id").and_then(Value::as_u64) {
+                    subscribed.insert(id);
+                }
+            }
+            json!({ "type": "response", "status": status.as_u16(), "body": body })
+        }
+        ClientMessage::Subscribe { ids } => {
+            subscribed.extend(ids);
+            subscriptions(subscribed)
+        }
+        ClientMessage::Unsubscribe { ids } => {
+            for id in ids {
+                subscribed.remove(&id);
+            }
+            subscriptions(subscribed)
+        }
+    }
+}
+
+fn push(event: &Event) -> Value {
+    let data = serde_json::from_str(&event.data).unwrap_or(Value::Null);
+    json!({ "type": "event", "event": event.name, "data": data })
+}
+
+fn subscriptions(subscribed: &HashSet<u64>) -> Value {
+    let mut ids: Vec<u64> = subscribed.iter().copied().collect();
+    ids.sort_unstable();
+    json!({ "type": "subscribed", "ids": ids })
+}
+
+fn error(message: &str) -> Value {
+    json!({ "type": "error", "error": message })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ark_serialize::Compress;
+    use axum::routing::get;
+    use axum::Router;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::Message as ClientFrame;
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+    use super::*;
+    use crate::testing;
+    use crate::{delete_snark, DeleteParams};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// Serve the WebSocket API on a free port and connect to it
+    async fn connect(state: &SharedState) -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("port is free");
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        let app = Router::new().route("/", get(upgrade)).with_state(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        tokio_tungstenite::connect_async(url).await.expect("upgrade succeeds").0
+    }
+
+    async fn send(client: &mut Client, message: Value) {
+        client.send(ClientFrame::Text(message.to_string())).await.expect("message is sent");
+    }
+
+    async fn receive(client: &mut Client) -> Value {
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("reply arrives")
+                .expect("session stays open")
+                .expect("frame is readable");
+            if let ClientFrame::Text(text) = frame {
+                return serde_json::from_str(&text).expect("replies are JSON");
+            }
+        }
+    }
+
+    async fn delete(state: &SharedState, id: u64) {
+        let params = DeleteParams { actor: None };
+        delete_snark(State(state.clone()), None, axum::extract::Path(id), axum::extract::Query(params)).await;
+    }
+
+    #[tokio::test]
+    async fn subscriptions_follow_requests() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let mut client = connect(&state).await;
+
+        send(&mut client, json!({ "type": "subscribe", "ids": [3, 1, 3] })).await;
+        assert_eq!(receive(&mut client).await, json!({ "type": "subscribed", "ids": [1, 3] }));
+        send(&mut client, json!({ "type": "unsubscribe", "ids": [3, 7] })).await;
+        assert_eq!(receive(&mut client).await, json!({ "type": "subscribed", "ids": [1] }));
+
+        send(&mut client, json!({ "type": "shout" })).await;
+        assert_eq!(receive(&mut client).await["type"], "error");
+        client.send(ClientFrame::Binary(b"{}".to_vec())).await.unwrap();
+        assert_eq!(receive(&mut client).await["type"], "error");
+    }
+
+    #[tokio::test]
+    async fn pushes_events_for_subscribed_snarks_only() {
+        let (state, _dir) = testing::state(testing::no_webhooks()).await;
+        let (followed, other) = {
+            let mut app = state.nockapp.write().await;
+            (testing::submit(&mut app, "alice", "", "a").await, testing::submit(&mut app, "alice", "", "b").await)
+        };
+        let mut client = connect(&state).await;
+        send(&mut client, json!({ "type": "subscribe", "ids": [followed] })).await;
+        receive(&mut client).await;
+
+        delete(&state, other).await;
+        delete(&state, followed).await;
+        let event = receive(&mut client).await;
+        assert_eq!(event["type"], "event");
+        assert_eq!(event["event"], "deleted");
+        assert_eq!(event["data"]["id"], followed);
+
+        // Deleted SNARKs are dropped from the subscriptions
+        send(&mut client, json!({ "type": "subscribe", "ids": [] })).await;
+        assert_eq!(receive(&mut client).await, json!({ "type": "subscribed", "ids": [] }));
+    }
+
+    #[tokio::test]
+    async fn submissions_reply_and_subscribe() {
+        let (state, mut jobs, _dir) = testing::state_with_jobs(testing::no_webhooks(), 1).await;
+        let fixture = testing::groth16::<ark_bn254::Bn254>(Compress::Yes);
+        let mut client = connect(&state).await;
+
+        send(&mut client, json!({
+            "type": "submit",
+            "proof": BASE64.encode(fixture.proof),
+            "verification_key": BASE64.encode(fixture.vk),
+            "public_inputs": ["15"],
+            "proof_system": "groth16",
+            "submitter": "alice",
+        }))
+        .await;
+        let reply = receive(&mut client).await;
+        assert_eq!(reply["type"], "response");
+        assert_eq!(reply["status"], 201, "{}", reply);
+        let id = reply["body"]["id"].as_u64().expect("reply carries the ID");
+        assert_eq!(jobs.recv().await, Some(id));
+
+        // The submission's own event arrives once it is subscribed
+        let event = receive(&mut client).await;
+        assert_eq!((event["event"].as_str(), event["data"]["id"].as_u64()), (Some("submitted"), Some(id)));
+    }
+}
@@ -0,0 +1,188 @@
+#!/usr/bin/env python3
+"""Write state-v1.jam, a v1 kernel state for the snapshot upgrade test.
+
+The state is [%v1 snarks=(map @ud snark-entry) next-id=@ud] with three
+SNARKs (IDs 1 to 3; 4 was deleted), built as Hoon would: the map is a treap
+ordered by +gor and +mor, so the kernel can look entries up after the
+upgrade. Run from this directory to regenerate the fixture.
+"""
+
+from pathlib import Path
+
+# @da of the Unix epoch, in whole seconds
+UNIX_EPOCH_DA = 0x8000000CCE9E0D80
+
+
+def cord(text):
+    return int.from_bytes(text.encode(), "little")
+
+
+def da(unix_seconds):
+    return (UNIX_EPOCH_DA + unix_seconds) << 64
+
+
+def hoon_list(items):
+    noun = 0
+    for item in reversed(items):
+        noun = (item, noun)
+    return noun
+
+
+def unit(value):
+    return 0 if value is None else (0, value)
+
+
+def tup(*items):
+    noun = items[-1]
+    for item in reversed(items[:-1]):
+        noun = (item, noun)
+    return noun
+
+
+def murmur3(data, seed):
+    c1, c2, mask = 0xCC9E2D51, 0x1B873593, 0xFFFFFFFF
+    h = seed & mask
+    rotl = lambda x, r: ((x << r) | (x >> (32 - r))) & mask
+    blocks = len(data) // 4
+    for i in range(blocks):
+        k = int.from_bytes(data[4 * i : 4 * i + 4], "little")
+        k = rotl((k * c1) & mask, 15) * c2 & mask
+        h = (rotl(h ^ k, 13) * 5 + 0xE6546B64) & mask
+    tail = data[4 * blocks :]
+    if tail:
+        k = int.from_bytes(tail, "little")
+        k = rotl((k * c1) & mask, 15) * c2 & mask
+        h ^= k
+    h ^= len(data)
+    h ^= h >> 16
+    h = (h * 0x85EBCA6B) & mask
+    h ^= h >> 13
+    h = (h * 0xC2B2AE35) & mask
+    h ^= h >> 16
+    return h
+
+
+def mum(seed, fallback, key):
+    data = key.to_bytes((key.bit_length() + 7) // 8, "little")
+    for i in range(8):
+        haz = murmur3(data, seed + i)
+        ham = (haz >> 31) ^ (haz & 0x7FFFFFFF)
+        if ham:
+            return ham
+    return fallback
+
+
+def mug(noun):
+    if isinstance(noun, int):
+        return mum(0xCAFEBABE, 0x7FFF, noun)
+    return mum(0xDEADBEEF, 0xFFFE, mug(noun[0]) | (mug(noun[1]) << 32))
+
+
+def dor(a, b):
+    if a == b:
+        return True
+    if not isinstance(a, int):
+        if isinstance(b, int):
+            return False
+        return dor(a[1], b[1]) if a[0] == b[0] else dor(a[0], b[0])
+    return not isinstance(b, int) or a < b
+
+
+def gor(a, b):
+    c, d = mug(a), mug(b)
+    return dor(a, b) if c == d else c < d
+
+
+def mor(a, b):
+    c, d = mug(mug(a)), mug(mug(b))
+    return dor(a, b) if c == d else c < d
+
+
+def put(tree, key, value):
+    """++put:by, on maps as None or [(key, value), left, right]"""
+    if tree is None:
+        return [(key, value), None, None]
+    (k, _), left, right = tree
+    if key == k:
+        return [(key, value), left, right]
+    if gor(key, k):
+        d = put(left, key, value)
+        if mor(k, d[0][0]):
+            return [tree[0], d, right]
+        return [d[0], d[1], [tree[0], d[2], right]]
+    d = put(right, key, value)
+    if mor(k, d[0][0]):
+        return [tree[0], left, d]
+    return [d[0], [tree[0], left, d[1]], d[2]]
+
+
+def map_noun(tree):
+    if tree is None:
+        return 0
+    node, left, right = tree
+    return (node, (map_noun(left), map_noun(right)))
+
+
+def jam(noun):
+    bits = []
+
+    def mat(a):
+        if a == 0:
+            bits.append(1)
+            return
+        b = a.bit_length()
+        c = b.bit_length()
+        bits.extend([0] * c + [1])
+        bits.extend((b >> i) & 1 for i in range(c - 1))
+        bits.extend((a >> i) & 1 for i in range(b))
+
+    stack = [noun]
+    while stack:
+        n = stack.pop()
+        if isinstance(n, int):
+            bits.append(0)
+            mat(n)
+        else:
+            bits.extend([1, 0])
+            stack.append(n[1])
+            stack.append(n[0])
+    value = sum(bit << i for i, bit in enumerate(bits))
+    return value.to_bytes((value.bit_length() + 7) // 8, "little")
+
+
+def entry(id, proof, inputs, vk, system, submitter, submitted, status, error, notes):
+    return tup(
+        id,
+        cord(proof),
+        hoon_list([cord(i) for i in inputs]),
+        cord(vk),
+        cord(system),
+        cord(submitter),
+        da(submitted),
+        cord(status),
+        unit(None if error is None else cord(error)),
+        cord(notes),
+    )
+
+
+def main():
+    here = Path(__file__).parent
+    data = here.parent.parent.parent / "test-data"
+    proof = (data / "sample-groth16-proof.txt").read_text().strip()
+    vk = (data / "sample-verification-key.txt").read_text().strip()
+
+    entries = [
+        entry(1, proof, ["1", "2"], vk, "groth16", "alice", 1_700_000_000, "pending", None, "first"),
+        entry(2, "cGxvbms=", [], "a2V5", "plonk", "bob", 1_700_000_060, "failed", "bad proof", ""),
+        entry(3, "c3Rhcms=", ["0x2a"], "", "stark", "José", 1_700_000_120, "verified", None, "line one\nline two"),
+    ]
+    tree = None
+    for noun in entries:
+        tree = put(tree, noun[0], noun)
+    state = tup(cord("v1"), map_noun(tree), 5)
+    (here / "state-v1.jam").write_bytes(jam(state))
+
+
+if __name__ == "__main__":
+    assert mug(0) == 0x79FF04E8, hex(mug(0))
+    main()
@@ -1,6 +1,21 @@
 // API base URL
 const API_BASE = '/api/v1';
 
+// SNARKs fetched per page of the list
+const PAGE_SIZE = 50;
+
+// Cursor for the next page of the list, or null when all are shown
+let nextCursor = null;
+
+// Pending list reload after live events, so a burst causes one fetch
+let reloadTimer = null;
+
+// Where the API key is kept between visits, when the server asks for one
+const API_KEY_STORAGE = 'prover-api-key';
+
+// Live event stream, reopened whenever a new API key is stored
+let eventSource = null;
+
 // DOM elements
 const submitForm = document.getElementById('submit-form');
 const submitResult = document.getElementById('submit-result');
@@ -8,15 +23,18 @@ const snarkList = document.getElementById('snark-list');
 const refreshBtn = document.getElementById('refresh-btn');
 
 // Initialize
-document.addEventListener('DOMContentLoaded', () => {
+document.addEventListener('DOMContentLoaded', async () => {
     setupEventListeners();
-    loadSnarks();
+    // The first fetch asks for a key if the server needs one, so the
+    // stream opens after it with the key in hand
+    await loadSnarks();
+    subscribeToEvents();
     log('Prover UI loaded');
 });
 
 function setupEventListeners() {
     submitForm.addEventListener('submit', handleSubmit);
-    refreshBtn.addEventListener('click', loadSnarks);
+    refreshBtn.addEventListener('click', () => loadSnarks());
 }
 
 // Handle SNARK submission
@@ -38,20 +56,20 @@ async function handleSubmit(e) {
         notes: formData.get('notes') || null
     };
 
-    // Validate Base64
-    if (!isValidBase64(submission.proof)) {
-        showResult('✗ Invalid Base64 encoding in proof data', 'error');
+    // Validate Base64, or JSON such as a snarkjs file
+    if (!isValidBase64(submission.proof) && !isValidJson(submission.proof)) {
+        showResult('✗ Proof data must be Base64 or JSON', 'error');
         return;
     }
-    if (!isValidBase64(submission.verification_key)) {
-        showResult('✗ Invalid Base64 encoding in verification key', 'error');
+    if (!isValidBase64(submission.verification_key) && !isValidJson(submission.verification_key)) {
+        showResult('✗ Verification key must be Base64 or JSON', 'error');
         return;
     }
 
     try {
         showResult('Submitting SNARK...', 'info');
         
-        const response = await fetch(`${API_BASE}/snark`, {
+        const response = await apiFetch('/snark', {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
@@ -64,7 +82,7 @@ async function handleSubmit(e) {
         if (response.ok) {
             showResult(`✓ SNARK submitted successfully! ID: ${result.id || 'unknown'}`, 'success');
             submitForm.reset();
-            setTimeout(loadSnarks, 500); // Refresh list
+            loadSnarks();
         } else {
             showResult(`✗ Error: ${result.error || 'Submission failed'}`, 'error');
         }
@@ -74,26 +92,62 @@ async function handleSubmit(e) {
     }
 }
 
-// Load and display SNARKs
-async function loadSnarks() {
+// Reload the list live as SNARKs are submitted, verified and deleted
+// EventSource reconnects by itself if the stream drops, but not after a
+// 401, so a stream opened without a valid key is replaced once one is stored
+function subscribeToEvents() {
+    if (eventSource) {
+        eventSource.close();
+    }
+    // EventSource cannot send headers, so the key goes in the query string
+    const key = localStorage.getItem(API_KEY_STORAGE);
+    const query = key ? `?api_key=${encodeURIComponent(key)}` : '';
+    const events = new EventSource(`${API_BASE}/events${query}`);
+    eventSource = events;
+    for (const name of ['submitted', 'status', 'deleted']) {
+        events.addEventListener(name, (e) => {
+            const data = JSON.parse(e.data);
+            log(`SNARK #${data.id} ${name}${data.change ? `: ${data.change.to}` : ''}`);
+            scheduleReload();
+        });
+    }
+    // Missed events while falling behind; resync from scratch
+    events.addEventListener('lagged', () => scheduleReload());
+}
+
+function scheduleReload() {
+    clearTimeout(reloadTimer);
+    reloadTimer = setTimeout(() => loadSnarks(), 250);
+}
+
+// Load and display SNARKs, newest first
+// With `more` set, appends the next page instead of reloading
+async function loadSnarks(more = false) {
+    const params = new URLSearchParams({ sort: 'submitted', order: 'desc', limit: PAGE_SIZE });
+    if (more && nextCursor !== null) {
+        params.set('cursor', nextCursor);
+    }
+
     try {
-        const response = await fetch(`${API_BASE}/snarks`);
+        const response = await apiFetch(`/snarks?${params}`);
         const data = await response.json();
 
         if (data.snarks && data.snarks.length > 0) {
-            displaySnarks(data.snarks);
-        } else {
+            displaySnarks(data.snarks, more);
+        } else if (!more) {
             snarkList.innerHTML = 'No SNARKs submitted yet.';
         }
+        nextCursor = data.next_cursor ?? null;
+        showLoadMore();
     } catch (error) {
         console.error('Error loading SNARKs:', error);
         snarkList.innerHTML = 'Failed to load SNARKs';
     }
 }
 
-// Display SNARKs in the list
-function displaySnarks(snarks) {
-    snarkList.innerHTML = snarks.map(snark => `
+// Display SNARKs in the list, replacing or appending to what is shown
+function displaySnarks(snarks, append = false) {
+    const html = snarks.map(snark => `
         
             
                 #${snark.id}
@@ -128,16 +182,47 @@ function displaySnarks(snarks) {
             
         
     `).join('');
+
+    if (append) {
+        snarkList.insertAdjacentHTML('beforeend', html);
+    } else {
+        snarkList.innerHTML = html;
+    }
+}
+
+// Show a "Load more" button after the list while more pages remain
+function showLoadMore() {
+    document.getElementById('load-more-btn')?.remove();
+    if (nextCursor === null) return;
+
+    const button = document.createElement('button');
+    button.id = 'load-more-btn';
+    button.className = 'btn-secondary';
+    button.textContent = 'Load more';
+    button.addEventListener('click', () => loadSnarks(true));
+    snarkList.after(button);
 }
 
 // View SNARK details
 async function viewDetails(id) {
     try {
-        const response = await fetch(`${API_BASE}/snark/${id}`);
+        const response = await apiFetch(`/snark/${id}`);
         const snark = await response.json();
         
         if (response.ok) {
-            alert(`SNARK #${id}\nStatus: ${snark.status}\nProof System: ${snark.proof_system}\nSubmitter: ${snark.submitter}`);
+            const lines = [
+                `SNARK #${id}`,
+                `Status: ${snark.status}`,
+                `Proof System: ${snark.proof_system}`,
+                `Submitter: ${snark.submitter}`,
+                `Submitted: ${formatDate(snark.submitted)}`,
+                `Public Inputs: ${snark.public_inputs.length ? snark.public_inputs.join(', ') : 'none'}`,
+                `Proof: ${snark.proof.length} chars`,
+                `Verification Key: ${snark.verification_key.length} chars`,
+            ];
+            if (snark.error_message) lines.push(`Error: ${snark.error_message}`);
+            if (snark.notes) lines.push(`Notes: ${snark.notes}`);
+            alert(lines.join('\n'));
         } else {
             alert(`Error: ${snark.error || 'Failed to load details'}`);
         }
@@ -154,13 +239,13 @@ async function deleteSnark(id) {
     }
 
     try {
-        const response = await fetch(`${API_BASE}/snark/${id}`, {
+        const response = await apiFetch(`/snark/${id}`, {
             method: 'DELETE'
         });
 
         if (response.ok) {
             log(`SNARK #${id} deleted`);
-            loadSnarks(); // Refresh list
+            loadSnarks();
         } else {
             const data = await response.json();
             alert(`Failed to delete: ${data.error || 'Unknown error'}`);
@@ -171,6 +256,26 @@ async function deleteSnark(id) {
     }
 }
 
+// Fetch from the API with the stored key
+// On 401 asks for a key once, stores it and tries again
+async function apiFetch(path, options = {}, retried = false) {
+    const key = localStorage.getItem(API_KEY_STORAGE);
+    const headers = { ...(options.headers || {}) };
+    if (key) headers['X-API-Key'] = key;
+
+    const response = await fetch(`${API_BASE}${path}`, { ...options, headers });
+    if (response.status !== 401 || retried) {
+        return response;
+    }
+    const entered = prompt('This server needs an API key:');
+    if (!entered) {
+        return response;
+    }
+    localStorage.setItem(API_KEY_STORAGE, entered.trim());
+    subscribeToEvents();
+    return apiFetch(path, options, true);
+}
+
 // Show result message
 function showResult(message, type) {
     submitResult.textContent = message;
@@ -199,13 +304,25 @@ function isValidBase64(str) {
     }
 }
 
+// Utility: Validate JSON document
+function isValidJson(str) {
+    if (!str || !str.trim().startsWith('{')) return false;
+    try {
+        JSON.parse(str);
+        return true;
+    } catch (err) {
+        return false;
+    }
+}
+
 // Utility: Escape HTML
 function escapeHtml(unsafe) {
     return unsafe
-        .replace(/&/g, "&")
-        .replace(//g, ">")
-        .replace(/"/g, """)
-        .replace(/'/g, "'");
+        .replace(/&/g, "&amp;")
+        .replace(/</g, "&lt;")
+        .replace(/>/g, "&gt;")
+        .replace(/"/g, "&quot;")
+        .replace(/'/g, "&#039;");
 }
 
 // Utility: Log to console